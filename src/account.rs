@@ -0,0 +1,177 @@
+use crate::{
+    client::Client,
+    error::{FairOSError, FairOSUserError},
+};
+
+use futures::future::join_all;
+use secrecy::SecretString;
+
+pub struct AccountManager {
+    client: Client,
+    current: Option<String>,
+}
+
+impl AccountManager {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            current: None,
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+
+    pub fn into_client(self) -> Client {
+        self.client
+    }
+
+    pub fn accounts(&self) -> Vec<&str> {
+        self.client.usernames()
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn switch(&mut self, username: &str) -> Result<(), FairOSError> {
+        if self.client.cookie(username).is_none() {
+            return Err(FairOSError::User(FairOSUserError::NotLoggedIn));
+        }
+        self.current = Some(username.into());
+        Ok(())
+    }
+
+    pub async fn login(
+        &mut self,
+        username: &str,
+        password: &SecretString,
+    ) -> Result<(), FairOSError> {
+        self.client.login(username, password).await?;
+        if self.current.is_none() {
+            self.current = Some(username.into());
+        }
+        Ok(())
+    }
+
+    pub async fn check_all(&self) -> Vec<(String, Result<bool, FairOSError>)> {
+        let usernames = self.accounts();
+        let results = join_all(
+            usernames
+                .iter()
+                .map(|username| self.client.is_logged_in(username)),
+        )
+        .await;
+        usernames
+            .into_iter()
+            .map(String::from)
+            .zip(results)
+            .collect()
+    }
+
+    pub async fn logout(&mut self, username: &str) -> Result<(), FairOSError> {
+        if self.client.cookie(username).is_none() {
+            return Err(FairOSError::User(FairOSUserError::NotLoggedIn));
+        }
+        self.client.logout(username).await?;
+        if self.current.as_deref() == Some(username) {
+            self.current = None;
+        }
+        Ok(())
+    }
+
+    pub async fn logout_all(&mut self) -> Vec<(String, Result<(), FairOSError>)> {
+        let usernames = self
+            .accounts()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(usernames.len());
+        for username in usernames {
+            let res = self.client.logout(&username).await;
+            if res.is_err() {
+                log::warn!("failed to log out {}", username);
+            }
+            results.push((username, res));
+        }
+        self.current = None;
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccountManager, Client, FairOSError, FairOSUserError};
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    #[tokio::test]
+    async fn test_switch_unknown_account_fails() {
+        let mut accounts = AccountManager::new(Client::new());
+        let res = accounts.switch(&random_name());
+        assert_eq!(res, Err(FairOSError::User(FairOSUserError::NotLoggedIn)));
+        assert_eq!(accounts.current(), None);
+    }
+
+    #[tokio::test]
+    async fn test_accounts_succeeds() {
+        let mut client = Client::new();
+        client.set_cookie("bob", "cookie".into());
+        client.set_cookie("alice", "cookie".into());
+        let mut accounts = AccountManager::new(client);
+        assert_eq!(accounts.accounts(), vec!["alice", "bob"]);
+        let res = accounts.switch("bob");
+        assert!(res.is_ok());
+        assert_eq!(accounts.current(), Some("bob"));
+    }
+
+    #[tokio::test]
+    async fn test_check_all_and_logout_all_succeeds() {
+        let mut accounts = AccountManager::new(Client::new());
+        let mut usernames = Vec::new();
+        for _ in 0..3 {
+            let username = random_name();
+            let password = random_password();
+            let res = accounts
+                .client_mut()
+                .signup(&username, &password, None)
+                .await;
+            assert!(res.is_ok());
+            usernames.push(username);
+        }
+        let res = accounts.switch(&usernames[1]);
+        assert!(res.is_ok());
+        let res = accounts.check_all().await;
+        assert_eq!(res.len(), 3);
+        assert!(res.iter().all(|(_, res)| matches!(res, Ok(true))));
+        let res = accounts.logout_all().await;
+        assert!(res.iter().all(|(_, res)| res.is_ok()));
+        assert!(accounts.accounts().is_empty());
+        assert_eq!(accounts.current(), None);
+    }
+}
@@ -0,0 +1,608 @@
+use crate::{
+    doc::indexed_fields,
+    error::{FairOSBackupError, FairOSError},
+    transfer::parent_dir,
+    BlockSize, Client, Compression, Expr, FieldType, IndexType,
+};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::TryStreamExt;
+use mime::Mime;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+pub(crate) const MANIFEST_VERSION: u32 = 1;
+pub(crate) const MANIFEST_PATH: &str = "manifest.json";
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackupFile {
+    pub path: String,
+    pub content_type: String,
+    pub size: u32,
+    pub block_size: u32,
+    pub compression: Option<Compression>,
+    pub creation_time: u64,
+    pub modification_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackupKeyValueStore {
+    pub name: String,
+    pub index_type: IndexType,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackupDocumentDatabase {
+    pub name: String,
+    pub fields: Vec<(String, FieldType)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub pod: String,
+    pub created_time: u64,
+    pub dirs: Vec<String>,
+    pub files: Vec<BackupFile>,
+    pub kv_stores: Vec<BackupKeyValueStore>,
+    pub doc_databases: Vec<BackupDocumentDatabase>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub pod: String,
+    pub dirs: Vec<String>,
+    pub files: Vec<String>,
+    pub kv_stores: Vec<(String, usize)>,
+    pub doc_databases: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct BackupKeyValuePair {
+    pub key: String,
+    pub value: String,
+}
+
+pub(crate) fn file_entry_path(path: &str) -> String {
+    format!("files/{}", path.trim_start_matches('/'))
+}
+
+pub(crate) fn kv_entry_path(store: &str) -> String {
+    format!("kv/{}.json", store)
+}
+
+pub(crate) fn doc_entry_path(database: &str) -> String {
+    format!("docs/{}.json", database)
+}
+
+fn block_size_bytes(block_size: &BlockSize) -> u32 {
+    match block_size.to_bytes() {
+        BlockSize::Bytes(n) => n,
+        _ => unreachable!(),
+    }
+}
+
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    mtime: u64,
+) -> Result<(), FairOSError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .map_err(|_| FairOSError::Backup(FairOSBackupError::Io))
+}
+
+fn read_backup<P: AsRef<Path>>(
+    path: P,
+) -> Result<(BackupManifest, HashMap<String, Vec<u8>>), FairOSError> {
+    let file = File::open(path).map_err(|_| FairOSError::Backup(FairOSBackupError::Io))?;
+    let decoder =
+        zstd::Decoder::new(file).map_err(|_| FairOSError::Backup(FairOSBackupError::Io))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut entries = HashMap::new();
+    for entry in archive
+        .entries()
+        .map_err(|_| FairOSError::Backup(FairOSBackupError::InvalidArchive))?
+    {
+        let mut entry =
+            entry.map_err(|_| FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+        let path = entry
+            .path()
+            .ok()
+            .and_then(|path| path.to_str().map(String::from))
+            .ok_or(FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+        let mut buf = Vec::new();
+        entry
+            .read_to_end(&mut buf)
+            .map_err(|_| FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+        entries.insert(path, buf);
+    }
+    let manifest = entries
+        .remove(MANIFEST_PATH)
+        .and_then(|buf| serde_json::from_slice::<BackupManifest>(&buf).ok())
+        .ok_or(FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(FairOSError::Backup(FairOSBackupError::UnsupportedVersion));
+    }
+    Ok((manifest, entries))
+}
+
+fn take_entry(entries: &mut HashMap<String, Vec<u8>>, path: &str) -> Result<Vec<u8>, FairOSError> {
+    entries
+        .remove(path)
+        .ok_or(FairOSError::Backup(FairOSBackupError::InvalidArchive))
+}
+
+impl Client {
+    pub async fn backup_pod<P: AsRef<Path>>(
+        &self,
+        username: &str,
+        pod: &str,
+        path: P,
+    ) -> Result<BackupManifest, FairOSError> {
+        let (dirs, entries) = self.list_tree(username, pod, "/").await?;
+        let mut files = Vec::with_capacity(entries.len());
+        for (path, entry) in entries {
+            let info = self.file_info(username, pod, &path).await?;
+            files.push(BackupFile {
+                path,
+                content_type: entry.content_type,
+                size: info.size,
+                block_size: block_size_bytes(&info.block_size),
+                compression: info.compression,
+                creation_time: info.creation_time,
+                modification_time: info.modification_time,
+            });
+        }
+        let kv_stores = self
+            .list_kv_stores(username, pod)
+            .await?
+            .into_iter()
+            .map(|store| BackupKeyValueStore {
+                index_type: store.index_type(),
+                name: store.name,
+            })
+            .collect::<Vec<_>>();
+        let doc_databases = self
+            .list_doc_databases(username, pod)
+            .await?
+            .into_iter()
+            .map(|database| BackupDocumentDatabase {
+                name: database.name,
+                fields: database.fields,
+            })
+            .collect::<Vec<_>>();
+        let created_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            pod: pod.into(),
+            created_time,
+            dirs,
+            files,
+            kv_stores,
+            doc_databases,
+        };
+
+        let file = File::create(path).map_err(|_| FairOSError::Backup(FairOSBackupError::Io))?;
+        let encoder = zstd::Encoder::new(file, ZSTD_LEVEL)
+            .map_err(|_| FairOSError::Backup(FairOSBackupError::Io))?;
+        let mut builder = tar::Builder::new(encoder);
+        append(
+            &mut builder,
+            MANIFEST_PATH,
+            &serde_json::to_vec_pretty(&manifest).unwrap(),
+            created_time,
+        )?;
+        for file in &manifest.files {
+            let buf = self.download_buffer(username, pod, &file.path).await?;
+            append(
+                &mut builder,
+                &file_entry_path(&file.path),
+                &buf,
+                file.modification_time,
+            )?;
+        }
+        for store in &manifest.kv_stores {
+            self.open_kv_store(username, pod, &store.name).await?;
+            let pairs = self
+                .kv_seek(username, pod, &store.name, "", None, None)
+                .await?
+                .map_ok(|(key, value)| BackupKeyValuePair { key, value })
+                .try_collect::<Vec<_>>()
+                .await?;
+            append(
+                &mut builder,
+                &kv_entry_path(&store.name),
+                &serde_json::to_vec(&pairs).unwrap(),
+                created_time,
+            )?;
+        }
+        for database in &manifest.doc_databases {
+            self.open_doc_database(username, pod, &database.name)
+                .await?;
+            let count = self
+                .count_documents(username, pod, &database.name, Expr::All)
+                .await?;
+            let docs = self
+                .find_documents::<serde_json::Value>(
+                    username,
+                    pod,
+                    &database.name,
+                    Expr::All,
+                    Some(count),
+                )
+                .await?;
+            append(
+                &mut builder,
+                &doc_entry_path(&database.name),
+                &serde_json::to_vec(&docs).unwrap(),
+                created_time,
+            )?;
+        }
+        builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(|_| FairOSError::Backup(FairOSBackupError::Io))?;
+        Ok(manifest)
+    }
+
+    pub async fn restore_pod<P: AsRef<Path>>(
+        &self,
+        path: P,
+        username: &str,
+        pod: Option<&str>,
+        password: &SecretString,
+        dry_run: bool,
+    ) -> Result<RestoreReport, FairOSError> {
+        let (manifest, mut entries) = read_backup(path)?;
+        let pod = pod.unwrap_or(&manifest.pod);
+        let mut report = RestoreReport {
+            pod: pod.into(),
+            ..Default::default()
+        };
+        if !dry_run {
+            self.create_pod(username, pod, password).await?;
+            self.open_pod(username, pod, password).await?;
+        }
+        for dir in &manifest.dirs {
+            if !dry_run {
+                self.mkdir(username, pod, dir).await?;
+            }
+            report.dirs.push(dir.clone());
+        }
+        for file in &manifest.files {
+            let buf = take_entry(&mut entries, &file_entry_path(&file.path))?;
+            if !dry_run {
+                let name = file.path.rsplit('/').next().unwrap();
+                let mime = file
+                    .content_type
+                    .parse::<Mime>()
+                    .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+                self.upload_buffer(
+                    username,
+                    pod,
+                    parent_dir(&file.path),
+                    name,
+                    buf.as_slice(),
+                    mime,
+                    BlockSize::from(file.block_size as u64),
+                    file.compression,
+                )
+                .await?;
+            }
+            report.files.push(file.path.clone());
+        }
+        for store in &manifest.kv_stores {
+            let buf = take_entry(&mut entries, &kv_entry_path(&store.name))?;
+            let pairs = serde_json::from_slice::<Vec<BackupKeyValuePair>>(&buf)
+                .map_err(|_| FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+            if !dry_run {
+                self.create_kv_store(username, pod, &store.name, store.index_type)
+                    .await?;
+                self.open_kv_store(username, pod, &store.name).await?;
+                for pair in &pairs {
+                    self.put_kv_raw(username, pod, &store.name, &pair.key, &pair.value)
+                        .await?;
+                }
+            }
+            report.kv_stores.push((store.name.clone(), pairs.len()));
+        }
+        for database in &manifest.doc_databases {
+            let buf = take_entry(&mut entries, &doc_entry_path(&database.name))?;
+            let docs = serde_json::from_slice::<Vec<serde_json::Value>>(&buf)
+                .map_err(|_| FairOSError::Backup(FairOSBackupError::InvalidArchive))?;
+            if !dry_run {
+                let fields = indexed_fields(&database.fields);
+                self.create_doc_database(username, pod, &database.name, &fields, true)
+                    .await?;
+                self.open_doc_database(username, pod, &database.name)
+                    .await?;
+                for doc in &docs {
+                    match doc["id"].as_str() {
+                        Some(id) => {
+                            self.put_document_with_id(username, pod, &database.name, id, doc)
+                                .await?
+                        }
+                        None => {
+                            self.put_document(username, pod, &database.name, doc)
+                                .await?;
+                        }
+                    }
+                }
+            }
+            report
+                .doc_databases
+                .push((database.name.clone(), docs.len()));
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        append, block_size_bytes, doc_entry_path, file_entry_path, kv_entry_path,
+        BackupKeyValuePair, MANIFEST_PATH, MANIFEST_VERSION,
+    };
+    use crate::{
+        BackupDocumentDatabase, BackupFile, BackupKeyValueStore, BackupManifest, BlockSize, Client,
+        Compression, FairOSBackupError, FairOSError, FieldType, IndexType, RestoreReport,
+    };
+    use futures::TryStreamExt;
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+    use std::{fs, io::Read};
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn test_manifest(version: u32) -> BackupManifest {
+        BackupManifest {
+            version,
+            pod: "cat-photos".into(),
+            created_time: 0,
+            dirs: vec!["/photos".into()],
+            files: vec![BackupFile {
+                path: "/photos/cat.txt".into(),
+                content_type: "text/plain".into(),
+                size: 4,
+                block_size: 1_000,
+                compression: None,
+                creation_time: 0,
+                modification_time: 0,
+            }],
+            kv_stores: vec![BackupKeyValueStore {
+                name: "table1".into(),
+                index_type: IndexType::Str,
+            }],
+            doc_databases: vec![BackupDocumentDatabase {
+                name: "table2".into(),
+                fields: vec![("id".into(), FieldType::Str), ("s1".into(), FieldType::Str)],
+            }],
+        }
+    }
+
+    fn write_test_backup(path: &str, manifest: &BackupManifest) {
+        let file = fs::File::create(path).unwrap();
+        let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0).unwrap());
+        append(
+            &mut builder,
+            MANIFEST_PATH,
+            &serde_json::to_vec(manifest).unwrap(),
+            0,
+        )
+        .unwrap();
+        append(
+            &mut builder,
+            &file_entry_path("/photos/cat.txt"),
+            b"meow",
+            0,
+        )
+        .unwrap();
+        let pairs = vec![BackupKeyValuePair {
+            key: "key1".into(),
+            value: "\"value1\"".into(),
+        }];
+        append(
+            &mut builder,
+            &kv_entry_path("table1"),
+            &serde_json::to_vec(&pairs).unwrap(),
+            0,
+        )
+        .unwrap();
+        let docs = serde_json::json!([{ "id": "1", "s1": "cat" }, { "id": "2", "s1": "dog" }]);
+        append(
+            &mut builder,
+            &doc_entry_path("table2"),
+            &serde_json::to_vec(&docs).unwrap(),
+            0,
+        )
+        .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restore_pod_dry_run_succeeds() {
+        let fairos = Client::new();
+        let path = format!("{}.tar.zst", random_name());
+        write_test_backup(&path, &test_manifest(MANIFEST_VERSION));
+        let res = fairos
+            .restore_pod(
+                &path,
+                "username",
+                Some("cat-backup"),
+                &random_password(),
+                true,
+            )
+            .await;
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            res.unwrap(),
+            RestoreReport {
+                pod: "cat-backup".into(),
+                dirs: vec!["/photos".into()],
+                files: vec!["/photos/cat.txt".into()],
+                kv_stores: vec![("table1".into(), 1)],
+                doc_databases: vec![("table2".into(), 2)],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_pod_unsupported_version_fails() {
+        let fairos = Client::new();
+        let path = format!("{}.tar.zst", random_name());
+        write_test_backup(&path, &test_manifest(MANIFEST_VERSION + 1));
+        let res = fairos
+            .restore_pod(&path, "username", None, &random_password(), true)
+            .await;
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Backup(FairOSBackupError::UnsupportedVersion)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entry_paths() {
+        assert_eq!(file_entry_path("/photos/cat.jpg"), "files/photos/cat.jpg");
+        assert_eq!(kv_entry_path("table1"), "kv/table1.json");
+        assert_eq!(doc_entry_path("table1"), "docs/table1.json");
+        assert_eq!(block_size_bytes(&BlockSize::Kilobytes(4)), 4_000);
+    }
+
+    #[tokio::test]
+    async fn test_backup_pod_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                Some(Compression::Gzip),
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .create_kv_store(&username, &pod, "table1", IndexType::Str)
+            .await;
+        assert!(res.is_ok());
+        for (key, value) in [("key1", "value1"), ("key2", "value2"), ("key3", "value3")] {
+            let res = fairos
+                .put_kv_pair(&username, &pod, "table1", key, value)
+                .await;
+            assert!(res.is_ok());
+        }
+        let res = fairos
+            .create_doc_database(&username, &pod, "table2", &[("s1", FieldType::Str)], true)
+            .await;
+        assert!(res.is_ok());
+        let path = format!("{}.tar.zst", random_name());
+        let res = fairos.backup_pod(&username, &pod, &path).await;
+        assert!(res.is_ok());
+        let manifest = res.unwrap();
+        assert_eq!(manifest.dirs, vec!["/photos".to_string()]);
+        assert_eq!(manifest.files[0].path, "/photos/cat.txt");
+        assert_eq!(manifest.files[0].compression, Some(Compression::Gzip));
+        assert_eq!(manifest.kv_stores[0].index_type, IndexType::Str);
+
+        let file = fs::File::open(&path).unwrap();
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file).unwrap());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.path().unwrap().to_str(), Some(MANIFEST_PATH));
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf).unwrap();
+        let res = serde_json::from_slice::<BackupManifest>(&buf);
+        assert_eq!(res.unwrap(), manifest);
+
+        let restored = random_name();
+        let res = fairos
+            .restore_pod(&path, &username, Some(&restored), &password, false)
+            .await;
+        fs::remove_file(&path).unwrap();
+        assert!(res.is_ok());
+        let mut tables = Vec::new();
+        for pod in [&pod, &restored] {
+            let res = fairos.open_kv_store(&username, pod, "table1").await;
+            assert!(res.is_ok());
+            let res = fairos
+                .kv_seek(&username, pod, "table1", "", None, None)
+                .await;
+            let res = res.unwrap().try_collect::<Vec<_>>().await;
+            tables.push(res.unwrap());
+        }
+        assert_eq!(tables[0].len(), 3);
+        assert_eq!(tables[0], tables[1]);
+    }
+
+    #[tokio::test]
+    async fn test_restore_pod_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let path = format!("{}.tar.zst", random_name());
+        write_test_backup(&path, &test_manifest(MANIFEST_VERSION));
+        let res = fairos
+            .restore_pod(&path, &username, None, &password, false)
+            .await;
+        fs::remove_file(&path).unwrap();
+        assert!(res.is_ok());
+        let res = fairos
+            .download_buffer(&username, "cat-photos", "/photos/cat.txt")
+            .await;
+        assert_eq!(res.unwrap().as_ref(), b"meow");
+        let res = fairos
+            .get_kv_pair::<String>(&username, "cat-photos", "table1", "key1")
+            .await;
+        assert_eq!(res.unwrap(), "value1");
+    }
+}
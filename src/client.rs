@@ -1,11 +1,23 @@
 use core::{str::FromStr, time::Duration};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::Mutex,
+};
 
 use bytes::Bytes;
-use hyper::header::{CONTENT_TYPE, COOKIE, SET_COOKIE};
-use hyper::{client::HttpConnector, Body, Request, StatusCode, Uri};
+use hyper::body::HttpBody;
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE, COOKIE, RANGE, SET_COOKIE};
+use hyper::{client::HttpConnector, Body, Request, Response, StatusCode, Uri};
 use hyper_tls::HttpsConnector;
-use serde::{de::DeserializeOwned, Deserialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{
+    pod::PasswordProvider,
+    policy::{PasswordPolicy, UsernamePolicy},
+    runtime::{self, Executor},
+};
 
 const IDLE_TIMEOUT: u64 = 6000;
 const MAX_IDLE_PER_HOST: usize = 20;
@@ -23,15 +35,121 @@ pub(crate) struct MessageResponse {
     pub _code: u32,
 }
 
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Serializes credentials into a buffer of exactly the right size, so it is never
+// reallocated and leaves no copies behind that are freed without being zeroized.
+pub(crate) fn secret_json<T: Serialize>(value: &T) -> Zeroizing<Vec<u8>> {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, value).unwrap();
+    let mut buf = Zeroizing::new(Vec::with_capacity(counter.0));
+    serde_json::to_writer(&mut *buf, value).unwrap();
+    buf
+}
+
+// Serializes a request body that contains credentials into a buffer which is zeroized
+// once the request has been sent and the last reference to it is dropped.
+pub(crate) fn secret_body<T: Serialize>(value: &T) -> Bytes {
+    Bytes::from_owner(secret_json(value))
+}
+
 fn is_status_ok(status: StatusCode) -> bool {
     let status = status.as_u16();
     status >= 200 && status < 300
 }
 
+// Keeps the bytes of a streamed body that fall within `start..end` and drops the rest.
+pub(crate) struct RangeBuffer {
+    position: u64,
+    start: u64,
+    end: u64,
+    buf: Vec<u8>,
+}
+
+impl RangeBuffer {
+    pub fn new(start: u64, end: u64) -> Self {
+        Self {
+            position: 0,
+            start,
+            end,
+            buf: Vec::with_capacity((end - start) as usize),
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        let chunk_start = self.position;
+        let chunk_end = chunk_start + chunk.len() as u64;
+        self.position = chunk_end;
+        let from = self.start.clamp(chunk_start, chunk_end);
+        let to = self.end.clamp(from, chunk_end);
+        self.buf
+            .extend_from_slice(&chunk[(from - chunk_start) as usize..(to - chunk_start) as usize]);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.position >= self.end
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        Bytes::from(self.buf)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
 pub struct Client {
     url: String,
     http_client: hyper::Client<HttpsConnector<HttpConnector>>,
     cookies: HashMap<String, String>,
+    timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+    password_policy: PasswordPolicy,
+    username_policy: UsernamePolicy,
+    open_pods: Mutex<HashMap<String, HashSet<String>>>,
+    password_provider: Option<Box<dyn PasswordProvider>>,
 }
 
 impl Client {
@@ -46,15 +164,72 @@ impl Client {
         let http_client = hyper::Client::builder()
             .pool_idle_timeout(Duration::from_secs(IDLE_TIMEOUT))
             .pool_max_idle_per_host(MAX_IDLE_PER_HOST)
+            .executor(Executor)
             .build::<_, Body>(https);
 
         Self {
             url,
             http_client,
             cookies: HashMap::new(),
+            timeout: None,
+            retry_policy: RetryPolicy::none(),
+            password_policy: PasswordPolicy::none(),
+            username_policy: UsernamePolicy::none(),
+            open_pods: Mutex::new(HashMap::new()),
+            password_provider: None,
         }
     }
 
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn password_policy(&self) -> &PasswordPolicy {
+        &self.password_policy
+    }
+
+    pub fn set_password_policy(&mut self, password_policy: PasswordPolicy) {
+        self.password_policy = password_policy;
+    }
+
+    pub fn username_policy(&self) -> &UsernamePolicy {
+        &self.username_policy
+    }
+
+    pub fn set_username_policy(&mut self, username_policy: UsernamePolicy) {
+        self.username_policy = username_policy;
+    }
+
+    pub fn password_provider(&self) -> Option<&dyn PasswordProvider> {
+        self.password_provider.as_deref()
+    }
+
+    pub fn set_password_provider(&mut self, password_provider: Option<Box<dyn PasswordProvider>>) {
+        self.password_provider = password_provider;
+    }
+
+    pub fn usernames(&self) -> Vec<&str> {
+        let mut usernames = self
+            .cookies
+            .keys()
+            .map(|username| username.as_str())
+            .collect::<Vec<_>>();
+        usernames.sort_unstable();
+        usernames
+    }
+
     pub fn cookie(&self, username: &str) -> Option<&str> {
         if let Some(cookie) = self.cookies.get(username) {
             Some(cookie.as_str())
@@ -82,6 +257,43 @@ impl Client {
         }
     }
 
+    pub fn open_pods(&self, username: &str) -> Vec<String> {
+        let open_pods = self.open_pods.lock().unwrap();
+        let mut pods = open_pods
+            .get(username)
+            .map(|pods| pods.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        pods.sort_unstable();
+        pods
+    }
+
+    pub fn is_pod_open(&self, username: &str, pod: &str) -> bool {
+        let open_pods = self.open_pods.lock().unwrap();
+        open_pods
+            .get(username)
+            .is_some_and(|pods| pods.contains(pod))
+    }
+
+    pub(crate) fn set_pod_open(&self, username: &str, pod: &str, open: bool) {
+        let mut open_pods = self.open_pods.lock().unwrap();
+        if open {
+            open_pods
+                .entry(username.into())
+                .or_default()
+                .insert(pod.into());
+        } else if let Some(pods) = open_pods.get_mut(username) {
+            pods.remove(pod);
+        }
+    }
+
+    pub(crate) fn take_open_pods(&self, username: &str) -> Vec<String> {
+        let mut open_pods = self.open_pods.lock().unwrap();
+        open_pods
+            .remove(username)
+            .map(|pods| pods.into_iter().collect())
+            .unwrap_or_default()
+    }
+
     fn make_uri(&self, path: &str, query: HashMap<&str, &str>) -> Uri {
         let query = if query.is_empty() {
             "".to_string()
@@ -98,29 +310,96 @@ impl Client {
         Uri::from_str(&uri_str).unwrap()
     }
 
+    // Requests are only retried when they could not have reached the server, or when
+    // they timed out and are safe to repeat.
+    async fn send<F>(&self, idempotent: bool, make_req: F) -> Result<Response<Body>, RequestError>
+    where
+        F: Fn() -> Request<Body>,
+    {
+        let mut attempt = 0;
+        loop {
+            let req = runtime::compat(self.http_client.request(make_req()));
+            let (res, retryable) = match self.timeout {
+                Some(timeout) => match runtime::timeout(timeout, req).await {
+                    Ok(res) => {
+                        let retryable = matches!(&res, Err(err) if err.is_connect());
+                        (res.map_err(|_| RequestError::CouldNotConnect), retryable)
+                    }
+                    Err(_) => (Err(RequestError::CouldNotConnect), idempotent),
+                },
+                None => {
+                    let res = req.await;
+                    let retryable = matches!(&res, Err(err) if err.is_connect());
+                    (res.map_err(|_| RequestError::CouldNotConnect), retryable)
+                }
+            };
+            if res.is_ok() || !retryable || attempt >= self.retry_policy.max_retries {
+                return res;
+            }
+            log::warn!(
+                "request failed, retrying ({}/{})",
+                attempt + 1,
+                self.retry_policy.max_retries
+            );
+            runtime::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    async fn read_body(res: Response<Body>) -> Result<Bytes, RequestError> {
+        runtime::compat(hyper::body::to_bytes(res))
+            .await
+            .map_err(|_| RequestError::CouldNotConnect)
+    }
+
+    // Reads a response that contains credentials. The chunks are zeroized as they are
+    // copied into a buffer that is zeroized in turn, including when it has to grow.
+    async fn read_secret_body(res: Response<Body>) -> Result<Zeroizing<Vec<u8>>, RequestError> {
+        let len = res
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse().ok())
+            .unwrap_or(0);
+        let mut buf = Zeroizing::new(Vec::with_capacity(len));
+        let mut body = res.into_body();
+        while let Some(chunk) = runtime::compat(body.data()).await {
+            let chunk = chunk.map_err(|_| RequestError::CouldNotConnect)?;
+            if buf.capacity() - buf.len() < chunk.len() {
+                let mut grown = Zeroizing::new(Vec::with_capacity(2 * (buf.len() + chunk.len())));
+                grown.extend_from_slice(&buf);
+                buf = grown;
+            }
+            buf.extend_from_slice(&chunk);
+            if let Ok(mut chunk) = chunk.try_into_mut() {
+                chunk[..].zeroize();
+            }
+        }
+        Ok(buf)
+    }
+
     pub(crate) async fn get<T: DeserializeOwned>(
         &self,
         path: &str,
         query: HashMap<&str, &str>,
         cookie: Option<&str>,
     ) -> Result<T, RequestError> {
-        let mut req = Request::builder()
-            .method("GET")
-            .uri(self.make_uri(path, query))
-            .body(Body::from(""))
-            .unwrap();
-        if let Some(cookie) = cookie {
-            req.headers_mut()
-                .insert(COOKIE, format!("fairOS-dfs={}", cookie).parse().unwrap());
-        }
-
+        let uri = self.make_uri(path, query);
         let res = self
-            .http_client
-            .request(req)
-            .await
-            .map_err(|_| RequestError::CouldNotConnect)?;
+            .send(true, || {
+                let mut req = Request::builder()
+                    .method("GET")
+                    .uri(uri.clone())
+                    .body(Body::from(""))
+                    .unwrap();
+                if let Some(cookie) = cookie {
+                    req.headers_mut()
+                        .insert(COOKIE, format!("fairOS-dfs={}", cookie).parse().unwrap());
+                }
+                req
+            })
+            .await?;
         let status_ok = is_status_ok(res.status());
-        let buf = hyper::body::to_bytes(res).await.unwrap();
+        let buf = Self::read_body(res).await?;
 
         if status_ok {
             Ok(serde_json::from_slice(&buf).unwrap())
@@ -131,28 +410,28 @@ impl Client {
         }
     }
 
-    pub(crate) async fn post<T: DeserializeOwned>(
+    async fn send_post(
         &self,
         path: &str,
-        body: Vec<u8>,
+        body: Bytes,
         cookie: Option<&str>,
-    ) -> Result<(T, Option<String>), RequestError> {
-        let mut req = Request::builder()
-            .method("POST")
-            .uri(self.make_uri(path, HashMap::new()))
-            .header(CONTENT_TYPE, "application/json")
-            .body(Body::from(body))
-            .unwrap();
-        if let Some(cookie) = cookie {
-            req.headers_mut()
-                .insert(COOKIE, format!("fairOS-dfs={}", cookie).parse().unwrap());
-        }
-
+    ) -> Result<(Response<Body>, Option<String>), RequestError> {
+        let uri = self.make_uri(path, HashMap::new());
         let res = self
-            .http_client
-            .request(req)
-            .await
-            .map_err(|_| RequestError::CouldNotConnect)?;
+            .send(false, || {
+                let mut req = Request::builder()
+                    .method("POST")
+                    .uri(uri.clone())
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.clone()))
+                    .unwrap();
+                if let Some(cookie) = cookie {
+                    req.headers_mut()
+                        .insert(COOKIE, format!("fairOS-dfs={}", cookie).parse().unwrap());
+                }
+                req
+            })
+            .await?;
 
         let cookie = if let Some(cookie) = res.headers().get(SET_COOKIE) {
             let cookie_str = cookie.to_str().unwrap().to_string();
@@ -168,8 +447,38 @@ impl Client {
             None
         };
 
+        Ok((res, cookie))
+    }
+
+    pub(crate) async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: impl Into<Bytes>,
+        cookie: Option<&str>,
+    ) -> Result<(T, Option<String>), RequestError> {
+        let (res, cookie) = self.send_post(path, body.into(), cookie).await?;
         let status_ok = is_status_ok(res.status());
-        let buf = hyper::body::to_bytes(res).await.unwrap();
+        let buf = Self::read_body(res).await?;
+
+        if status_ok {
+            let des = serde_json::from_slice(&buf).unwrap();
+            Ok((des, cookie))
+        } else {
+            let res: MessageResponse = serde_json::from_slice(&buf).unwrap();
+            log::error!("{}", res.message);
+            Err(RequestError::Message(res.message))
+        }
+    }
+
+    pub(crate) async fn post_secret<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: impl Into<Bytes>,
+        cookie: Option<&str>,
+    ) -> Result<(T, Option<String>), RequestError> {
+        let (res, cookie) = self.send_post(path, body.into(), cookie).await?;
+        let status_ok = is_status_ok(res.status());
+        let buf = Self::read_secret_body(res).await?;
 
         if status_ok {
             let des = serde_json::from_slice(&buf).unwrap();
@@ -184,24 +493,24 @@ impl Client {
     pub(crate) async fn delete<T: DeserializeOwned>(
         &self,
         path: &str,
-        body: Vec<u8>,
+        body: impl Into<Bytes>,
         cookie: &str,
     ) -> Result<T, RequestError> {
-        let req = Request::builder()
-            .method("DELETE")
-            .uri(self.make_uri(path, HashMap::new()))
-            .header(CONTENT_TYPE, "application/json")
-            .header(COOKIE, format!("fairOS-dfs={}", cookie))
-            .body(Body::from(body))
-            .unwrap();
-
+        let uri = self.make_uri(path, HashMap::new());
+        let body = body.into();
         let res = self
-            .http_client
-            .request(req)
-            .await
-            .map_err(|_| RequestError::CouldNotConnect)?;
+            .send(false, || {
+                Request::builder()
+                    .method("DELETE")
+                    .uri(uri.clone())
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(COOKIE, format!("fairOS-dfs={}", cookie))
+                    .body(Body::from(body.clone()))
+                    .unwrap()
+            })
+            .await?;
         let status_ok = is_status_ok(res.status());
-        let buf = hyper::body::to_bytes(res).await.unwrap();
+        let buf = Self::read_body(res).await?;
 
         if status_ok {
             Ok(serde_json::from_slice(&buf).unwrap())
@@ -220,28 +529,29 @@ impl Client {
         cookie: &str,
         compression: Option<&str>,
     ) -> Result<T, RequestError> {
-        let mut req = Request::builder()
-            .method("POST")
-            .uri(self.make_uri(path, HashMap::new()))
-            .header(
-                CONTENT_TYPE,
-                format!("multipart/form-data;boundary={}", boundary),
-            )
-            .header(COOKIE, format!("fairOS-dfs={}", cookie))
-            .body(Body::from(body))
-            .unwrap();
-        if let Some(compression) = compression {
-            req.headers_mut()
-                .insert("fairOS-dfs-Compression", compression.parse().unwrap());
-        }
-
+        let uri = self.make_uri(path, HashMap::new());
+        let body = Bytes::from(body);
         let res = self
-            .http_client
-            .request(req)
-            .await
-            .map_err(|_| RequestError::CouldNotConnect)?;
+            .send(false, || {
+                let mut req = Request::builder()
+                    .method("POST")
+                    .uri(uri.clone())
+                    .header(
+                        CONTENT_TYPE,
+                        format!("multipart/form-data;boundary={}", boundary),
+                    )
+                    .header(COOKIE, format!("fairOS-dfs={}", cookie))
+                    .body(Body::from(body.clone()))
+                    .unwrap();
+                if let Some(compression) = compression {
+                    req.headers_mut()
+                        .insert("fairOS-dfs-Compression", compression.parse().unwrap());
+                }
+                req
+            })
+            .await?;
         let status_ok = is_status_ok(res.status());
-        let buf = hyper::body::to_bytes(res).await.unwrap();
+        let buf = Self::read_body(res).await?;
 
         if status_ok {
             Ok(serde_json::from_slice(&buf).unwrap())
@@ -259,24 +569,24 @@ impl Client {
         boundary: &str,
         cookie: &str,
     ) -> Result<Bytes, RequestError> {
-        let req = Request::builder()
-            .method("POST")
-            .uri(self.make_uri(path, HashMap::new()))
-            .header(
-                CONTENT_TYPE,
-                format!("multipart/form-data;boundary={}", boundary),
-            )
-            .header(COOKIE, format!("fairOS-dfs={}", cookie))
-            .body(Body::from(body))
-            .unwrap();
-
+        let uri = self.make_uri(path, HashMap::new());
+        let body = Bytes::from(body);
         let res = self
-            .http_client
-            .request(req)
-            .await
-            .map_err(|_| RequestError::CouldNotConnect)?;
+            .send(true, || {
+                Request::builder()
+                    .method("POST")
+                    .uri(uri.clone())
+                    .header(
+                        CONTENT_TYPE,
+                        format!("multipart/form-data;boundary={}", boundary),
+                    )
+                    .header(COOKIE, format!("fairOS-dfs={}", cookie))
+                    .body(Body::from(body.clone()))
+                    .unwrap()
+            })
+            .await?;
         let status_ok = is_status_ok(res.status());
-        let buf = hyper::body::to_bytes(res).await.unwrap();
+        let buf = Self::read_body(res).await?;
 
         if status_ok {
             Ok(buf)
@@ -286,4 +596,56 @@ impl Client {
             Err(RequestError::Message(res.message))
         }
     }
+
+    // Asks the server for the bytes in `start..end` only. A server that ignores the
+    // range header sends the whole file, which is read up to `end` and then dropped.
+    pub(crate) async fn download_multipart_range(
+        &self,
+        path: &str,
+        body: Vec<u8>,
+        boundary: &str,
+        cookie: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Bytes, RequestError> {
+        let uri = self.make_uri(path, HashMap::new());
+        let body = Bytes::from(body);
+        let res = self
+            .send(true, || {
+                Request::builder()
+                    .method("POST")
+                    .uri(uri.clone())
+                    .header(
+                        CONTENT_TYPE,
+                        format!("multipart/form-data;boundary={}", boundary),
+                    )
+                    .header(COOKIE, format!("fairOS-dfs={}", cookie))
+                    .header(RANGE, format!("bytes={}-{}", start, end - 1))
+                    .body(Body::from(body.clone()))
+                    .unwrap()
+            })
+            .await?;
+        let status = res.status();
+        if !is_status_ok(status) {
+            let buf = Self::read_body(res).await?;
+            let res: MessageResponse = serde_json::from_slice(&buf).unwrap();
+            log::error!("{}", res.message);
+            return Err(RequestError::Message(res.message));
+        }
+
+        let mut range = if status == StatusCode::PARTIAL_CONTENT {
+            RangeBuffer::new(0, end - start)
+        } else {
+            RangeBuffer::new(start, end)
+        };
+        let mut body = res.into_body();
+        while !range.is_full() {
+            match runtime::compat(body.data()).await {
+                Some(Ok(chunk)) => range.push(&chunk),
+                Some(Err(_)) => return Err(RequestError::CouldNotConnect),
+                None => break,
+            }
+        }
+        Ok(range.into_bytes())
+    }
 }
@@ -0,0 +1,342 @@
+use crate::{error::FairOSError, Client};
+
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffEntry {
+    Dir(String),
+    File(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffReason {
+    Kind,
+    Size,
+    ModificationTime,
+    Content,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffChange {
+    pub path: String,
+    pub reasons: Vec<DiffReason>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DirDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub changed: Vec<DiffChange>,
+}
+
+impl DirDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SnapshotEntry {
+    Dir,
+    File { size: u32, modification_time: u64 },
+}
+
+// Entries of a tree keyed by their path relative to the root of the tree.
+pub(crate) type Snapshot = BTreeMap<String, SnapshotEntry>;
+
+pub(crate) fn relative_path(root: &str, path: &str) -> String {
+    match path.strip_prefix(root.trim_end_matches('/')) {
+        Some(path) if path.starts_with('/') => path.into(),
+        _ => path.into(),
+    }
+}
+
+fn diff_entry(path: &str, entry: &SnapshotEntry) -> DiffEntry {
+    match entry {
+        SnapshotEntry::Dir => DiffEntry::Dir(path.into()),
+        SnapshotEntry::File { .. } => DiffEntry::File(path.into()),
+    }
+}
+
+// Compares two snapshots and returns the differences along with the paths of files
+// that have the same size in both, which are the ones worth comparing by content.
+pub(crate) fn diff_snapshots(
+    a: &Snapshot,
+    b: &Snapshot,
+    compare_modification_time: bool,
+) -> (DirDiff, Vec<String>) {
+    let mut diff = DirDiff::default();
+    let mut same_size = Vec::new();
+    for (path, entry_a) in a {
+        let entry_b = match b.get(path) {
+            Some(entry_b) => entry_b,
+            None => {
+                diff.removed.push(diff_entry(path, entry_a));
+                continue;
+            }
+        };
+        let mut reasons = Vec::new();
+        match (entry_a, entry_b) {
+            (SnapshotEntry::Dir, SnapshotEntry::Dir) => {}
+            (
+                SnapshotEntry::File {
+                    size: size_a,
+                    modification_time: modification_time_a,
+                },
+                SnapshotEntry::File {
+                    size: size_b,
+                    modification_time: modification_time_b,
+                },
+            ) => {
+                if size_a != size_b {
+                    reasons.push(DiffReason::Size);
+                } else {
+                    same_size.push(path.clone());
+                }
+                if compare_modification_time && modification_time_a != modification_time_b {
+                    reasons.push(DiffReason::ModificationTime);
+                }
+            }
+            _ => reasons.push(DiffReason::Kind),
+        }
+        if !reasons.is_empty() {
+            diff.changed.push(DiffChange {
+                path: path.clone(),
+                reasons,
+            });
+        }
+    }
+    for (path, entry_b) in b {
+        if !a.contains_key(path) {
+            diff.added.push(diff_entry(path, entry_b));
+        }
+    }
+    (diff, same_size)
+}
+
+impl Client {
+    pub(crate) async fn snapshot(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+    ) -> Result<Snapshot, FairOSError> {
+        let (dirs, files) = self.list_tree(username, pod, dir).await?;
+        let mut snapshot = Snapshot::new();
+        for path in dirs {
+            snapshot.insert(relative_path(dir, &path), SnapshotEntry::Dir);
+        }
+        for (path, entry) in files {
+            snapshot.insert(
+                relative_path(dir, &path),
+                SnapshotEntry::File {
+                    size: entry.size,
+                    modification_time: entry.modification_time,
+                },
+            );
+        }
+        Ok(snapshot)
+    }
+
+    pub(crate) async fn content_hash(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+    ) -> Result<Vec<u8>, FairOSError> {
+        let buf = self.download_buffer(username, pod, path).await?;
+        Ok(Keccak256::digest(&buf).to_vec())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn diff_dirs(
+        &self,
+        username: &str,
+        pod_a: &str,
+        path_a: &str,
+        pod_b: &str,
+        path_b: &str,
+        compare_modification_time: bool,
+        compare_content: bool,
+    ) -> Result<DirDiff, FairOSError> {
+        let snapshot_a = self.snapshot(username, pod_a, path_a).await?;
+        let snapshot_b = self.snapshot(username, pod_b, path_b).await?;
+        let (mut diff, same_size) =
+            diff_snapshots(&snapshot_a, &snapshot_b, compare_modification_time);
+
+        if compare_content {
+            for path in same_size {
+                let hash_a = self
+                    .content_hash(
+                        username,
+                        pod_a,
+                        &format!("{}{}", path_a.trim_end_matches('/'), path),
+                    )
+                    .await?;
+                let hash_b = self
+                    .content_hash(
+                        username,
+                        pod_b,
+                        &format!("{}{}", path_b.trim_end_matches('/'), path),
+                    )
+                    .await?;
+                if hash_a == hash_b {
+                    continue;
+                }
+                match diff.changed.iter_mut().find(|change| change.path == path) {
+                    Some(change) => change.reasons.push(DiffReason::Content),
+                    None => diff.changed.push(DiffChange {
+                        path,
+                        reasons: vec![DiffReason::Content],
+                    }),
+                }
+            }
+            diff.changed.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        diff_snapshots, relative_path, Client, DiffChange, DiffEntry, DiffReason, Snapshot,
+        SnapshotEntry,
+    };
+    use crate::BlockSize;
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn file(size: u32, modification_time: u64) -> SnapshotEntry {
+        SnapshotEntry::File {
+            size,
+            modification_time,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relative_path() {
+        assert_eq!(relative_path("/", "/photos/cat.jpg"), "/photos/cat.jpg");
+        assert_eq!(relative_path("/photos", "/photos/cat.jpg"), "/cat.jpg");
+        assert_eq!(relative_path("/photos/", "/photos/cat.jpg"), "/cat.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_diff_snapshots() {
+        let mut a = Snapshot::new();
+        a.insert("/cats".into(), SnapshotEntry::Dir);
+        a.insert("/cats/cat.jpg".into(), file(100, 1));
+        a.insert("/cats/kitten.jpg".into(), file(50, 1));
+        a.insert("/dogs".into(), SnapshotEntry::Dir);
+        a.insert("/notes".into(), file(10, 1));
+        let mut b = Snapshot::new();
+        b.insert("/cats".into(), SnapshotEntry::Dir);
+        b.insert("/cats/cat.jpg".into(), file(100, 2));
+        b.insert("/cats/kitten.jpg".into(), file(60, 1));
+        b.insert("/birds".into(), SnapshotEntry::Dir);
+        b.insert("/notes".into(), SnapshotEntry::Dir);
+
+        let (diff, same_size) = diff_snapshots(&a, &b, false);
+        assert_eq!(diff.added, vec![DiffEntry::Dir("/birds".into())]);
+        assert_eq!(diff.removed, vec![DiffEntry::Dir("/dogs".into())]);
+        assert_eq!(
+            diff.changed,
+            vec![
+                DiffChange {
+                    path: "/cats/kitten.jpg".into(),
+                    reasons: vec![DiffReason::Size],
+                },
+                DiffChange {
+                    path: "/notes".into(),
+                    reasons: vec![DiffReason::Kind],
+                },
+            ]
+        );
+        assert_eq!(same_size, vec!["/cats/cat.jpg".to_string()]);
+
+        let (diff, _) = diff_snapshots(&a, &b, true);
+        assert_eq!(
+            diff.changed[0],
+            DiffChange {
+                path: "/cats/cat.jpg".into(),
+                reasons: vec![DiffReason::ModificationTime],
+            }
+        );
+
+        let (diff, _) = diff_snapshots(&a, &a, true);
+        assert!(diff.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_diff_dirs_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod_a = random_name();
+        let res = fairos.create_pod(&username, &pod_a, &password).await;
+        assert!(res.is_ok());
+        let pod_b = random_name();
+        let res = fairos.create_pod(&username, &pod_b, &password).await;
+        assert!(res.is_ok());
+        for (pod, content) in [(&pod_a, "meow"), (&pod_b, "purr")] {
+            let res = fairos
+                .upload_buffer(
+                    &username,
+                    pod,
+                    "/",
+                    "cat.txt",
+                    content.as_bytes(),
+                    mime::TEXT_PLAIN,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await;
+            assert!(res.is_ok());
+        }
+        let res = fairos.mkdir(&username, &pod_b, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .diff_dirs(&username, &pod_a, "/", &pod_b, "/", false, false)
+            .await;
+        assert!(res.is_ok());
+        let diff = res.unwrap();
+        assert_eq!(diff.added, vec![DiffEntry::Dir("/photos".into())]);
+        assert!(diff.changed.is_empty());
+        let res = fairos
+            .diff_dirs(&username, &pod_a, "/", &pod_b, "/", false, true)
+            .await;
+        assert!(res.is_ok());
+        let diff = res.unwrap();
+        assert_eq!(
+            diff.changed,
+            vec![DiffChange {
+                path: "/cat.txt".into(),
+                reasons: vec![DiffReason::Content],
+            }]
+        );
+    }
+}
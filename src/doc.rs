@@ -39,7 +39,8 @@ struct DocFindResponse {
     docs: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FieldType {
     Str,
     Number,
@@ -48,8 +49,18 @@ pub enum FieldType {
 
 #[derive(Debug, PartialEq)]
 pub struct DocumentDatabase {
-    name: String,
-    fields: Vec<(String, FieldType)>,
+    pub name: String,
+    pub fields: Vec<(String, FieldType)>,
+}
+
+// Leaves out the id field that fairOS adds to every database, so that the fields can be
+// passed back to `create_doc_database`.
+pub(crate) fn indexed_fields(fields: &[(String, FieldType)]) -> Vec<(&str, FieldType)> {
+    fields
+        .iter()
+        .filter(|(name, _)| name != "id")
+        .map(|(name, field_type)| (name.as_str(), *field_type))
+        .collect()
 }
 
 #[derive(Debug)]
@@ -105,6 +116,7 @@ impl Client {
         fields: &[(&str, FieldType)],
         mutable: bool,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let si = fields
             .iter()
             .map(|(field, field_type)| {
@@ -146,6 +158,7 @@ impl Client {
         pod: &str,
         name: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": name,
@@ -170,6 +183,7 @@ impl Client {
         pod: &str,
         name: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": name,
@@ -193,6 +207,7 @@ impl Client {
         username: &str,
         pod: &str,
     ) -> Result<Vec<DocumentDatabase>, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         let cookie = self.cookie(username).unwrap();
@@ -241,8 +256,22 @@ impl Client {
         doc: T,
     ) -> Result<String, FairOSError> {
         let id = Uuid::new_v4().to_string();
+        self.put_document_with_id(username, pod, database, &id, doc)
+            .await?;
+        Ok(id)
+    }
+
+    pub(crate) async fn put_document_with_id<T: Serialize>(
+        &self,
+        username: &str,
+        pod: &str,
+        database: &str,
+        id: &str,
+        doc: T,
+    ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut doc = json!(doc);
-        doc["id"] = json!(&id);
+        doc["id"] = json!(id);
         let data = json!({
             "pod_name": pod,
             "table_name": database,
@@ -259,7 +288,7 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Document(FairOSDocumentError::Error),
             })?;
-        Ok(id)
+        Ok(())
     }
 
     pub async fn get_document<T: DeserializeOwned>(
@@ -269,6 +298,7 @@ impl Client {
         database: &str,
         id: &str,
     ) -> Result<T, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("table_name", database);
@@ -292,6 +322,7 @@ impl Client {
         expr: Expr,
         limit: Option<u32>,
     ) -> Result<Vec<T>, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("table_name", database);
@@ -324,6 +355,7 @@ impl Client {
         database: &str,
         id: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": database,
@@ -350,6 +382,7 @@ impl Client {
         database: &str,
         expr: Expr,
     ) -> Result<u32, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": database,
@@ -376,6 +409,7 @@ impl Client {
         database: &str,
         buffer: R,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("table_name", database);
@@ -408,6 +442,7 @@ impl Client {
         database: &str,
         local_path: P,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("table_name", database);
@@ -435,6 +470,7 @@ impl Client {
         database: &str,
         file: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": database,
@@ -462,6 +498,7 @@ mod tests {
         distributions::{Alphanumeric, Uniform},
         thread_rng, Rng,
     };
+    use secrecy::SecretString;
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, PartialEq, Deserialize, Serialize)]
@@ -478,12 +515,13 @@ mod tests {
             .collect()
     }
 
-    fn random_password() -> String {
-        thread_rng()
+    fn random_password() -> SecretString {
+        let password = thread_rng()
             .sample_iter(Uniform::new_inclusive(0, 255))
             .take(8)
             .map(char::from)
-            .collect()
+            .collect::<String>();
+        SecretString::from(password)
     }
 
     #[tokio::test]
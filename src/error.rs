@@ -1,9 +1,41 @@
+use secrecy::{ExposeSecret, SecretString};
+
+// Returned when an account was deleted to change its password and could not be imported
+// again with either password. Importing the mnemonic restores it.
+#[derive(Debug)]
+pub struct AccountRecovery {
+    pub username: String,
+    pub mnemonic: SecretString,
+}
+
+impl PartialEq for AccountRecovery {
+    fn eq(&self, other: &Self) -> bool {
+        self.username == other.username
+            && self.mnemonic.expose_secret() == other.mnemonic.expose_secret()
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum FairOSUserError {
     Error,
     UsernameAlreadyExists,
     InvalidUsername,
     InvalidPassword,
+    InvalidMnemonic,
+    AddressMismatch,
+    NotLoggedIn,
+    PasswordTooShort,
+    PasswordTooLong,
+    PasswordMissingLowercase,
+    PasswordMissingUppercase,
+    PasswordMissingDigit,
+    PasswordMissingSymbol,
+    PasswordTooWeak,
+    UsernameTooShort,
+    UsernameTooLong,
+    UsernameForbidden,
+    MigrationIncomplete,
+    PasswordChangeFailed(AccountRecovery),
 }
 
 #[derive(Debug, PartialEq)]
@@ -14,11 +46,13 @@ pub enum FairOSPodError {
 #[derive(Debug, PartialEq)]
 pub enum FairOSFileSystemError {
     Error,
+    InvalidRange,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum FairOSKeyValueError {
     Error,
+    InvalidValue,
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,6 +60,37 @@ pub enum FairOSDocumentError {
     Error,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum FairOSKeystoreError {
+    InvalidPassphrase,
+    InvalidFormat,
+    Io,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FairOSBackupError {
+    Io,
+    InvalidArchive,
+    UnsupportedVersion,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FairOSSharingError {
+    InvalidReference,
+    InvalidUri,
+    UnexpectedResource,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FairOSTransferError {
+    InvalidPattern,
+    InvalidSyncState,
+    InvalidDestination,
+    DestinationExists,
+    VerificationFailed,
+    Io,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum FairOSError {
     CouldNotConnect,
@@ -34,4 +99,8 @@ pub enum FairOSError {
     FileSystem(FairOSFileSystemError),
     KeyValue(FairOSKeyValueError),
     Document(FairOSDocumentError),
+    Keystore(FairOSKeystoreError),
+    Backup(FairOSBackupError),
+    Sharing(FairOSSharingError),
+    Transfer(FairOSTransferError),
 }
@@ -1,15 +1,22 @@
 use crate::{
     client::{MessageResponse, RequestError},
     error::{FairOSError, FairOSFileSystemError},
-    Client,
+    Client, SharingReference,
 };
 
-use std::{collections::HashMap, fs, io::Read, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Read,
+    path::Path,
+    str::FromStr,
+};
 
 use bytes::Bytes;
+use futures::{stream, StreamExt};
 use mime::Mime;
 use multipart::client::lazy::Multipart;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 #[derive(Debug, Deserialize)]
@@ -134,6 +141,13 @@ pub struct FileEntry {
     pub access_time: u64,
 }
 
+#[derive(Debug, Default, PartialEq)]
+pub struct RemoveReport {
+    pub dirs: Vec<String>,
+    pub files: Vec<String>,
+    pub failures: Vec<(String, FairOSError)>,
+}
+
 #[derive(Debug)]
 pub struct DirInfo {
     pub pod: String,
@@ -146,7 +160,8 @@ pub struct DirInfo {
     pub no_of_files: u32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Compression {
     Gzip,
     Snappy,
@@ -189,7 +204,7 @@ pub struct SharedFileInfo {
     pub shared_time: u64,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BlockSize {
     Bytes(u32),
     Kilobytes(u32),
@@ -277,8 +292,22 @@ impl From<u64> for BlockSize {
     }
 }
 
+// A range has to end within the blocks of the file.
+fn range_end(info: &FileInfo, offset: u64, len: u64) -> Result<u64, FairOSError> {
+    let size = if info.blocks.is_empty() {
+        info.size as u64
+    } else {
+        info.blocks.iter().map(|block| block.size as u64).sum()
+    };
+    match offset.checked_add(len) {
+        Some(end) if end <= size => Ok(end),
+        _ => Err(FairOSError::FileSystem(FairOSFileSystemError::InvalidRange)),
+    }
+}
+
 impl Client {
     pub async fn mkdir(&self, username: &str, pod: &str, path: &str) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "dir_path": path,
@@ -298,6 +327,7 @@ impl Client {
     }
 
     pub async fn rmdir(&self, username: &str, pod: &str, path: &str) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "dir_path": path,
@@ -318,12 +348,87 @@ impl Client {
         Ok(())
     }
 
+    pub async fn rmdir_recursive(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+        concurrency: usize,
+        dry_run: bool,
+    ) -> Result<RemoveReport, FairOSError> {
+        let concurrency = concurrency.max(1);
+        let (dirs, files) = self.list_tree(username, pod, path).await?;
+        // Directories are removed a level at a time, deepest first.
+        let mut levels = BTreeMap::new();
+        for dir in dirs
+            .into_iter()
+            .chain(Some(path.trim_end_matches('/').to_string()).filter(|path| !path.is_empty()))
+        {
+            levels
+                .entry(dir.matches('/').count())
+                .or_insert_with(Vec::new)
+                .push(dir);
+        }
+        let mut report = RemoveReport::default();
+
+        if dry_run {
+            report.files = files.into_iter().map(|(path, _)| path).collect();
+            report.files.sort();
+            for (_, mut level) in levels.into_iter().rev() {
+                level.sort();
+                report.dirs.extend(level);
+            }
+            return Ok(report);
+        }
+
+        let mut removals = stream::iter(files)
+            .map(|(path, _)| async move {
+                let res = self.rm(username, pod, &path).await;
+                (path, res)
+            })
+            .buffer_unordered(concurrency);
+        while let Some((path, res)) = removals.next().await {
+            match res {
+                Ok(_) => report.files.push(path),
+                Err(err) => report.failures.push((path, err)),
+            }
+        }
+
+        for (_, mut level) in levels.into_iter().rev() {
+            // A directory above anything that failed to be removed cannot be empty.
+            level.retain(|dir| {
+                let prefix = format!("{}/", dir);
+                !report
+                    .failures
+                    .iter()
+                    .any(|(failed, _)| failed.starts_with(&prefix))
+            });
+            level.sort();
+            let mut removals = stream::iter(level)
+                .map(|dir| async move {
+                    let res = self.rmdir(username, pod, &dir).await;
+                    (dir, res)
+                })
+                .buffer_unordered(concurrency);
+            while let Some((dir, res)) = removals.next().await {
+                match res {
+                    Ok(_) => report.dirs.push(dir),
+                    Err(err) => report.failures.push((dir, err)),
+                }
+            }
+        }
+        report.files.sort();
+        report.failures.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+
     pub async fn ls(
         &self,
         username: &str,
         pod: &str,
         path: &str,
     ) -> Result<(Vec<DirEntry>, Vec<FileEntry>), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("dir_path", path);
@@ -374,6 +479,7 @@ impl Client {
         pod: &str,
         path: &str,
     ) -> Result<bool, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("dir_path", path);
@@ -394,6 +500,7 @@ impl Client {
         pod: &str,
         path: &str,
     ) -> Result<DirInfo, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("dir_path", path);
@@ -430,6 +537,7 @@ impl Client {
         block_size: BlockSize,
         compression: Option<Compression>,
     ) -> Result<String, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("dir_path", dir);
@@ -467,6 +575,7 @@ impl Client {
         block_size: BlockSize,
         compression: Option<Compression>,
     ) -> Result<String, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("dir_path", dir);
@@ -501,6 +610,7 @@ impl Client {
         pod: &str,
         path: &str,
     ) -> Result<Bytes, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("file_path", path);
@@ -527,6 +637,7 @@ impl Client {
         path: &str,
         local_path: P,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("file_path", path);
@@ -548,13 +659,57 @@ impl Client {
         Ok(())
     }
 
+    pub async fn read_range(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes, FairOSError> {
+        let info = self.file_info(username, pod, path).await?;
+        let end = range_end(&info, offset, len)?;
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+
+        let mut multipart = Multipart::new();
+        multipart.add_text("pod_name", pod);
+        multipart.add_text("file_path", path);
+        let mut prepared = multipart.prepare().unwrap();
+        let boundary = prepared.boundary().to_string();
+        let mut body = Vec::new();
+        prepared.read_to_end(&mut body).unwrap();
+
+        let cookie = self.cookie(username).unwrap();
+        let buf = self
+            .download_multipart_range(
+                "/file/download",
+                body,
+                boundary.as_str(),
+                cookie,
+                offset,
+                end,
+            )
+            .await
+            .map_err(|err| match err {
+                RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
+                RequestError::Message(_) => FairOSError::FileSystem(FairOSFileSystemError::Error),
+            })?;
+        if buf.len() as u64 != len {
+            return Err(FairOSError::FileSystem(FairOSFileSystemError::Error));
+        }
+        Ok(buf)
+    }
+
     pub async fn share_file(
         &self,
         username: &str,
         pod: &str,
         path: &str,
         receiver: &str,
-    ) -> Result<String, FairOSError> {
+    ) -> Result<SharingReference, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "file_path": path,
@@ -571,10 +726,11 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::FileSystem(FairOSFileSystemError::Error),
             })?;
-        Ok(res.file_sharing_reference)
+        res.file_sharing_reference.parse()
     }
 
     pub async fn rm(&self, username: &str, pod: &str, path: &str) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "file_path": path,
@@ -601,6 +757,7 @@ impl Client {
         pod: &str,
         path: &str,
     ) -> Result<FileInfo, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("file_path", path);
@@ -656,12 +813,13 @@ impl Client {
         &self,
         username: &str,
         pod: &str,
-        reference: &str,
+        reference: &SharingReference,
         dir: &str,
     ) -> Result<String, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
-        query.insert("sharing_ref", reference);
+        query.insert("sharing_ref", reference.as_str());
         query.insert("dir_path", dir);
         let cookie = self.cookie(username).unwrap();
         let res: FileReceiveResponse = self
@@ -678,11 +836,12 @@ impl Client {
         &self,
         username: &str,
         pod: &str,
-        reference: &str,
+        reference: &SharingReference,
     ) -> Result<SharedFileInfo, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
-        query.insert("sharing_ref", reference);
+        query.insert("sharing_ref", reference.as_str());
         let cookie = self.cookie(username).unwrap();
         let res: FileReceiveInfoResponse = self
             .get("/file/receiveinfo", query, Some(cookie))
@@ -719,12 +878,17 @@ impl Client {
 
 #[cfg(test)]
 mod tests {
-    use super::{Client, BlockSize, Compression};
+    use super::{range_end, BlockSize, Client, Compression, FileBlock, FileInfo};
+    use crate::{
+        client::RangeBuffer,
+        error::{FairOSError, FairOSFileSystemError},
+    };
     use bytes::Buf;
     use rand::{
         distributions::{Alphanumeric, Uniform},
         thread_rng, Rng,
     };
+    use secrecy::SecretString;
     use std::fs;
 
     fn random_name() -> String {
@@ -735,12 +899,13 @@ mod tests {
             .collect()
     }
 
-    fn random_password() -> String {
-        thread_rng()
+    fn random_password() -> SecretString {
+        let password = thread_rng()
             .sample_iter(Uniform::new_inclusive(0, 255))
             .take(8)
             .map(char::from)
-            .collect()
+            .collect::<String>();
+        SecretString::from(password)
     }
 
     #[tokio::test]
@@ -776,6 +941,53 @@ mod tests {
         assert_eq!(res.unwrap(), false);
     }
 
+    #[tokio::test]
+    async fn test_rmdir_recursive_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/Documents").await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/Documents/Notes").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/Documents/Notes",
+                "cat-names.txt",
+                "Peanut Butter, Cleo, Oreo, Smokey".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                None,
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .rmdir_recursive(&username, &pod, "/Documents", 4, true)
+            .await;
+        assert!(res.is_ok());
+        let report = res.unwrap();
+        assert_eq!(report.files, vec!["/Documents/Notes/cat-names.txt"]);
+        assert_eq!(report.dirs, vec!["/Documents/Notes", "/Documents"]);
+        let res = fairos.dir_exists(&username, &pod, "/Documents").await;
+        assert!(res.unwrap());
+        let res = fairos
+            .rmdir_recursive(&username, &pod, "/Documents", 4, false)
+            .await;
+        assert!(res.is_ok());
+        let report = res.unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(report.dirs, vec!["/Documents/Notes", "/Documents"]);
+        let res = fairos.dir_exists(&username, &pod, "/Documents").await;
+        assert!(!res.unwrap());
+    }
+
     #[tokio::test]
     async fn test_ls_succeeds() {
         let mut fairos = Client::new();
@@ -958,6 +1170,92 @@ mod tests {
         assert_eq!(&data, b"hello world");
     }
 
+    #[tokio::test]
+    async fn test_range_end() {
+        let block = |size| FileBlock {
+            name: "block".into(),
+            reference: "reference".into(),
+            size,
+            compressed_size: size,
+        };
+        let info = FileInfo {
+            pod: "pod".into(),
+            path: "/Documents".into(),
+            name: "hello.txt".into(),
+            content_type: None,
+            size: 11,
+            block_size: BlockSize::Bytes(8),
+            compression: None,
+            creation_time: 0,
+            modification_time: 0,
+            access_time: 0,
+            blocks: vec![block(8), block(3)],
+        };
+        assert_eq!(range_end(&info, 6, 5), Ok(11));
+        assert_eq!(range_end(&info, 11, 0), Ok(11));
+        assert_eq!(
+            range_end(&info, 6, 6),
+            Err(FairOSError::FileSystem(FairOSFileSystemError::InvalidRange))
+        );
+        assert_eq!(
+            range_end(&info, u64::MAX, 1),
+            Err(FairOSError::FileSystem(FairOSFileSystemError::InvalidRange))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_range_buffer() {
+        let mut range = RangeBuffer::new(3, 8);
+        for chunk in [&b"hel"[..], b"lo w", b"orld"] {
+            assert!(!range.is_full());
+            range.push(chunk);
+        }
+        assert!(range.is_full());
+        assert_eq!(range.into_bytes(), &b"lo wo"[..]);
+        let mut range = RangeBuffer::new(0, 5);
+        range.push(b"hello world");
+        assert!(range.is_full());
+        assert_eq!(range.into_bytes(), &b"hello"[..]);
+    }
+
+    #[tokio::test]
+    async fn test_read_range_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/Documents").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/Documents",
+                "hello.txt",
+                "hello world".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Bytes(4),
+                None,
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .read_range(&username, &pod, "/Documents/hello.txt", 3, 5)
+            .await;
+        assert_eq!(res.unwrap(), &b"lo wo"[..]);
+        let res = fairos
+            .read_range(&username, &pod, "/Documents/hello.txt", 6, 10)
+            .await;
+        assert_eq!(
+            res,
+            Err(FairOSError::FileSystem(FairOSFileSystemError::InvalidRange))
+        );
+    }
+
     #[tokio::test]
     async fn test_download_file_succeeds() {
         let mut fairos = Client::new();
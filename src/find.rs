@@ -0,0 +1,221 @@
+use crate::{
+    diff::relative_path,
+    error::{FairOSError, FairOSTransferError},
+    Client, FileEntry, WalkEntry, WalkEntryKind, WalkOrder,
+};
+
+use futures::{future, Stream, TryStreamExt};
+use globset::{Glob, GlobMatcher};
+
+// Every criterion that is set has to match. Sizes and times are inclusive.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FindQuery {
+    pub pattern: Option<String>,
+    pub min_size: Option<u32>,
+    pub max_size: Option<u32>,
+    pub modified_after: Option<u64>,
+    pub modified_before: Option<u64>,
+    pub content_type: Option<String>,
+}
+
+struct FindMatcher {
+    pattern: Option<GlobMatcher>,
+    content_type: Option<GlobMatcher>,
+    query: FindQuery,
+}
+
+fn glob_matcher(pattern: &Option<String>) -> Result<Option<GlobMatcher>, FairOSError> {
+    match pattern {
+        Some(pattern) => Glob::new(pattern)
+            .map(|glob| Some(glob.compile_matcher()))
+            .map_err(|_| FairOSError::Transfer(FairOSTransferError::InvalidPattern)),
+        None => Ok(None),
+    }
+}
+
+impl FindMatcher {
+    fn new(query: &FindQuery) -> Result<Self, FairOSError> {
+        Ok(Self {
+            pattern: glob_matcher(&query.pattern)?,
+            content_type: glob_matcher(&query.content_type)?,
+            query: query.clone(),
+        })
+    }
+
+    // Paths are matched relative to the root of the search, without a leading slash.
+    fn is_match(&self, path: &str, entry: &FileEntry) -> bool {
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(path.trim_start_matches('/')) {
+                return false;
+            }
+        }
+        if let Some(content_type) = &self.content_type {
+            if !content_type.is_match(&entry.content_type) {
+                return false;
+            }
+        }
+        let query = &self.query;
+        !(query.min_size.is_some_and(|size| entry.size < size)
+            || query.max_size.is_some_and(|size| entry.size > size)
+            || query
+                .modified_after
+                .is_some_and(|time| entry.modification_time < time)
+            || query
+                .modified_before
+                .is_some_and(|time| entry.modification_time > time))
+    }
+}
+
+impl Client {
+    pub fn find<'a>(
+        &'a self,
+        username: &'a str,
+        pod: &'a str,
+        root: &'a str,
+        query: &FindQuery,
+    ) -> Result<impl Stream<Item = Result<WalkEntry, FairOSError>> + 'a, FairOSError> {
+        let matcher = FindMatcher::new(query)?;
+        let filter = move |entry: &WalkEntry| match &entry.kind {
+            WalkEntryKind::Dir(_) => true,
+            WalkEntryKind::File(file) => matcher.is_match(&relative_path(root, &entry.path), file),
+        };
+        Ok(self
+            .walk(username, pod, root, WalkOrder::DepthFirst, None, filter)
+            .try_filter(|entry| future::ready(entry.is_file())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, FindMatcher, FindQuery};
+    use crate::{
+        error::{FairOSError, FairOSTransferError},
+        BlockSize, FileEntry,
+    };
+    use futures::TryStreamExt;
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn test_file_entry(content_type: &str, size: u32, modification_time: u64) -> FileEntry {
+        FileEntry {
+            name: "cat.jpg".into(),
+            content_type: content_type.into(),
+            size,
+            block_size: BlockSize::Kilobytes(1),
+            creation_time: modification_time,
+            modification_time,
+            access_time: modification_time,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_matcher() {
+        let query = FindQuery {
+            pattern: Some("**/*.jpg".into()),
+            min_size: Some(100),
+            max_size: Some(1000),
+            modified_after: Some(1_650_000_000),
+            content_type: Some("image/*".into()),
+            ..Default::default()
+        };
+        let matcher = FindMatcher::new(&query).unwrap();
+        let entry = test_file_entry("image/jpeg", 500, 1_650_000_000);
+        assert!(matcher.is_match("/photos/cat.jpg", &entry));
+        assert!(matcher.is_match("/cat.jpg", &entry));
+        assert!(!matcher.is_match("/photos/cat.png", &entry));
+        let entry = test_file_entry("text/plain", 500, 1_650_000_000);
+        assert!(!matcher.is_match("/photos/cat.jpg", &entry));
+        let entry = test_file_entry("image/jpeg", 5000, 1_650_000_000);
+        assert!(!matcher.is_match("/photos/cat.jpg", &entry));
+        let entry = test_file_entry("image/jpeg", 500, 1_600_000_000);
+        assert!(!matcher.is_match("/photos/cat.jpg", &entry));
+        let matcher = FindMatcher::new(&FindQuery::default()).unwrap();
+        assert!(matcher.is_match("/notes.txt", &entry));
+    }
+
+    #[tokio::test]
+    async fn test_find_invalid_pattern_fails() {
+        let fairos = Client::new();
+        let query = FindQuery {
+            pattern: Some("[".into()),
+            ..Default::default()
+        };
+        let res = fairos.find("username", "pod", "/", &query);
+        assert_eq!(
+            res.err(),
+            Some(FairOSError::Transfer(FairOSTransferError::InvalidPattern))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        for (name, content_type) in [("cat.jpg", mime::IMAGE_JPEG), ("cat.txt", mime::TEXT_PLAIN)] {
+            let res = fairos
+                .upload_buffer(
+                    &username,
+                    &pod,
+                    "/photos",
+                    name,
+                    "meow".as_bytes(),
+                    content_type,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await;
+            assert!(res.is_ok());
+        }
+        let query = FindQuery {
+            pattern: Some("**/*.jpg".into()),
+            ..Default::default()
+        };
+        let res = fairos.find(&username, &pod, "/", &query);
+        assert!(res.is_ok());
+        let res = res
+            .unwrap()
+            .map_ok(|entry| entry.path)
+            .try_collect::<Vec<_>>()
+            .await;
+        assert_eq!(res.unwrap(), vec!["/photos/cat.jpg"]);
+        let query = FindQuery {
+            content_type: Some("text/*".into()),
+            ..Default::default()
+        };
+        let res = fairos.find(&username, &pod, "/photos", &query);
+        let res = res
+            .unwrap()
+            .map_ok(|entry| entry.path)
+            .try_collect::<Vec<_>>()
+            .await;
+        assert_eq!(res.unwrap(), vec!["/photos/cat.txt"]);
+    }
+}
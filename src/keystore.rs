@@ -0,0 +1,395 @@
+use crate::{
+    client::secret_json,
+    error::{FairOSError, FairOSKeystoreError, FairOSUserError},
+    Client,
+};
+
+use std::{fs, path::Path};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use rand::{thread_rng, Rng};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const KEYSTORE_VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const KDF: &str = "scrypt";
+const SCRYPT_LOG_N: u8 = 13;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: SecretString,
+    pub mnemonic: Option<SecretString>,
+    pub address: String,
+}
+
+#[derive(Serialize)]
+struct CredentialsPlaintext<'a> {
+    username: &'a str,
+    password: &'a str,
+    mnemonic: Option<&'a str>,
+    address: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CipherParams {
+    nonce: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct KdfParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Crypto {
+    cipher: String,
+    ciphertext: String,
+    cipherparams: CipherParams,
+    kdf: String,
+    kdfparams: KdfParams,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    version: u32,
+    id: String,
+    crypto: Crypto,
+}
+
+fn derive_key(
+    passphrase: &SecretString,
+    params: &KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LEN]>, FairOSError> {
+    if params.dklen != KEY_LEN || !params.n.is_power_of_two() {
+        return Err(FairOSError::Keystore(FairOSKeystoreError::InvalidFormat));
+    }
+    let salt = hex::decode(&params.salt)
+        .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))?;
+    let log_n = params.n.trailing_zeros() as u8;
+    let scrypt_params = scrypt::Params::new(log_n, params.r, params.p, KEY_LEN)
+        .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    scrypt::scrypt(
+        passphrase.expose_secret().as_bytes(),
+        &salt,
+        &scrypt_params,
+        &mut *key,
+    )
+    .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))?;
+    Ok(key)
+}
+
+impl Keystore {
+    pub fn encrypt(credentials: &Credentials, passphrase: &SecretString) -> Self {
+        let mut rng = thread_rng();
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut nonce);
+
+        let kdfparams = KdfParams {
+            dklen: KEY_LEN,
+            n: 1 << SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(salt),
+        };
+        let key = derive_key(passphrase, &kdfparams).unwrap();
+        let cipher = Aes256Gcm::new_from_slice(&*key).unwrap();
+        let plaintext = secret_json(&CredentialsPlaintext {
+            username: &credentials.username,
+            password: credentials.password.expose_secret(),
+            mnemonic: credentials
+                .mnemonic
+                .as_ref()
+                .map(|mnemonic| mnemonic.expose_secret()),
+            address: &credentials.address,
+        });
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce), plaintext.as_slice())
+            .unwrap();
+
+        Self {
+            version: KEYSTORE_VERSION,
+            id: Uuid::new_v4().to_string(),
+            crypto: Crypto {
+                cipher: CIPHER.into(),
+                ciphertext: hex::encode(ciphertext),
+                cipherparams: CipherParams {
+                    nonce: hex::encode(nonce),
+                },
+                kdf: KDF.into(),
+                kdfparams,
+            },
+        }
+    }
+
+    pub fn decrypt(&self, passphrase: &SecretString) -> Result<Credentials, FairOSError> {
+        if self.version != KEYSTORE_VERSION
+            || self.crypto.cipher != CIPHER
+            || self.crypto.kdf != KDF
+        {
+            return Err(FairOSError::Keystore(FairOSKeystoreError::InvalidFormat));
+        }
+        let nonce: [u8; NONCE_LEN] = hex::decode(&self.crypto.cipherparams.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or(FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))?;
+        let ciphertext = hex::decode(&self.crypto.ciphertext)
+            .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))?;
+
+        let key = derive_key(passphrase, &self.crypto.kdfparams)?;
+        let cipher = Aes256Gcm::new_from_slice(&*key).unwrap();
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce), ciphertext.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidPassphrase))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FairOSError> {
+        let buf = fs::read(path).map_err(|_| FairOSError::Keystore(FairOSKeystoreError::Io))?;
+        serde_json::from_slice(&buf)
+            .map_err(|_| FairOSError::Keystore(FairOSKeystoreError::InvalidFormat))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FairOSError> {
+        let buf = serde_json::to_vec_pretty(self).unwrap();
+        fs::write(path, buf).map_err(|_| FairOSError::Keystore(FairOSKeystoreError::Io))
+    }
+}
+
+impl Client {
+    pub async fn login_with_keystore<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        passphrase: &SecretString,
+    ) -> Result<Credentials, FairOSError> {
+        let credentials = Keystore::load(path)?.decrypt(passphrase)?;
+        self.login(&credentials.username, &credentials.password)
+            .await?;
+        Ok(credentials)
+    }
+
+    pub async fn import_with_keystore<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        passphrase: &SecretString,
+    ) -> Result<String, FairOSError> {
+        let credentials = Keystore::load(path)?.decrypt(passphrase)?;
+        match &credentials.mnemonic {
+            Some(mnemonic) => {
+                self.import_with_mnemonic(&credentials.username, &credentials.password, mnemonic)
+                    .await
+            }
+            None => {
+                self.import_with_address(
+                    &credentials.username,
+                    &credentials.password,
+                    &credentials.address,
+                )
+                .await
+            }
+        }
+    }
+
+    pub async fn signup_with_keystore<P: AsRef<Path>>(
+        &mut self,
+        username: &str,
+        password: &SecretString,
+        mnemonic: Option<&SecretString>,
+        path: P,
+        passphrase: &SecretString,
+    ) -> Result<(String, Option<SecretString>), FairOSError> {
+        let (address, generated) = self.signup(username, password, mnemonic).await?;
+        let credentials = Credentials {
+            username: username.into(),
+            password: password.clone(),
+            mnemonic: mnemonic.cloned().or_else(|| generated.clone()),
+            address: address.clone(),
+        };
+        Keystore::encrypt(&credentials, passphrase).save(path)?;
+        Ok((address, generated))
+    }
+
+    pub async fn export_keystore<P: AsRef<Path>>(
+        &self,
+        username: &str,
+        password: &SecretString,
+        mnemonic: Option<&SecretString>,
+        path: P,
+        passphrase: &SecretString,
+    ) -> Result<(), FairOSError> {
+        let export = self.export_user(username).await?;
+        if let Some(mnemonic) = mnemonic {
+            if !Self::verify_address(mnemonic.expose_secret(), &export.address)? {
+                return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+            }
+        }
+        let credentials = Credentials {
+            username: export.username,
+            password: password.clone(),
+            mnemonic: mnemonic.cloned(),
+            address: export.address,
+        };
+        Keystore::encrypt(&credentials, passphrase).save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, Credentials, FairOSError, FairOSKeystoreError, Keystore};
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::{ExposeSecret, SecretString};
+    use std::fs;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn test_credentials() -> Credentials {
+        Credentials {
+            username: random_name(),
+            password: random_password(),
+            mnemonic: Some(SecretString::from(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            )),
+            address: "0x9858EfFD232B4033E47d90003D41EC34EcaEda94".into(),
+        }
+    }
+
+    fn assert_credentials_eq(a: &Credentials, b: &Credentials) {
+        assert_eq!(a.username, b.username);
+        assert_eq!(a.password.expose_secret(), b.password.expose_secret());
+        assert_eq!(
+            a.mnemonic.as_ref().map(|mnemonic| mnemonic.expose_secret()),
+            b.mnemonic.as_ref().map(|mnemonic| mnemonic.expose_secret()),
+        );
+        assert_eq!(a.address, b.address);
+    }
+
+    #[tokio::test]
+    async fn test_keystore_decrypt_succeeds() {
+        let credentials = test_credentials();
+        let passphrase = SecretString::from("passphrase");
+        let keystore = Keystore::encrypt(&credentials, &passphrase);
+        let res = keystore.decrypt(&passphrase);
+        assert!(res.is_ok());
+        assert_credentials_eq(&res.unwrap(), &credentials);
+    }
+
+    #[tokio::test]
+    async fn test_keystore_decrypt_invalid_passphrase_fails() {
+        let keystore = Keystore::encrypt(&test_credentials(), &SecretString::from("passphrase"));
+        let res = keystore.decrypt(&SecretString::from("wrong passphrase"));
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Keystore(FairOSKeystoreError::InvalidPassphrase),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keystore_save_load_succeeds() {
+        let credentials = test_credentials();
+        let passphrase = SecretString::from("passphrase");
+        let keystore = Keystore::encrypt(&credentials, &passphrase);
+        let path = format!("{}.json", random_name());
+        let res = keystore.save(&path);
+        assert!(res.is_ok());
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&credentials.username));
+        let res = Keystore::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(res.is_ok());
+        let loaded = res.unwrap();
+        assert_eq!(loaded, keystore);
+        assert_credentials_eq(&loaded.decrypt(&passphrase).unwrap(), &credentials);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_debug_is_redacted() {
+        let credentials = test_credentials();
+        let debug = format!("{:?}", credentials);
+        assert!(!debug.contains(credentials.password.expose_secret()));
+        assert!(!debug.contains("abandon"));
+    }
+
+    #[tokio::test]
+    async fn test_login_with_keystore_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let path = format!("{}.json", random_name());
+        let passphrase = SecretString::from("passphrase");
+        let res = fairos
+            .signup_with_keystore(&username, &password, None, &path, &passphrase)
+            .await;
+        assert!(res.is_ok());
+        let res = fairos.logout(&username).await;
+        assert!(res.is_ok());
+        let res = fairos.login_with_keystore(&path, &passphrase).await;
+        fs::remove_file(&path).unwrap();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().username, username);
+    }
+
+    #[tokio::test]
+    async fn test_import_with_keystore_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let (address, mnemonic) = res.unwrap();
+        let path = format!("{}.json", random_name());
+        let passphrase = SecretString::from("passphrase");
+        let res = fairos
+            .export_keystore(&username, &password, mnemonic.as_ref(), &path, &passphrase)
+            .await;
+        assert!(res.is_ok());
+        let res = fairos.delete_user(&username, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.import_with_keystore(&path, &passphrase).await;
+        fs::remove_file(&path).unwrap();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+    }
+}
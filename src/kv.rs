@@ -45,7 +45,8 @@ struct KvPresentResponse {
     present: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum IndexType {
     Str,
     Number,
@@ -57,40 +58,95 @@ pub struct KeyValueStore {
     pub indexes: Vec<String>,
 }
 
+impl KeyValueStore {
+    pub fn index_type(&self) -> IndexType {
+        match self.indexes.first().map(|index| index.as_str()) {
+            Some("NumberIndex") => IndexType::Number,
+            _ => IndexType::Str,
+        }
+    }
+}
+
+// The server answers with this error once a seek has run past the last pair.
+const KV_NO_NEXT_ELEMENT: &str = "no next element";
+
+type KvSeekItem = Result<(String, String), FairOSError>;
+type KvSeekFuture<'a> = Pin<Box<dyn Future<Output = Option<KvSeekItem>> + Send + 'a>>;
+
 pub struct KeyValueSeek<'a> {
     client: &'a Client,
     username: String,
     pod: String,
     store: String,
     limit: Option<u32>,
+    count: u32,
+    next: Option<KvSeekFuture<'a>>,
+}
+
+impl<'a> KeyValueSeek<'a> {
+    fn seek_next(&self) -> KvSeekFuture<'a> {
+        let client = self.client;
+        let username = self.username.clone();
+        let pod = self.pod.clone();
+        let store = self.store.clone();
+        Box::pin(async move {
+            let mut query = HashMap::new();
+            query.insert("pod_name", pod.as_str());
+            query.insert("table_name", store.as_str());
+            let cookie = client.cookie(&username).unwrap();
+            let res = match client
+                .get::<KvEntryGetResponse>("/kv/seek/next", query, Some(cookie))
+                .await
+            {
+                Ok(res) => res,
+                Err(RequestError::Message(msg)) if msg.contains(KV_NO_NEXT_ELEMENT) => return None,
+                Err(RequestError::CouldNotConnect) => {
+                    return Some(Err(FairOSError::CouldNotConnect))
+                }
+                Err(RequestError::Message(_)) => {
+                    return Some(Err(FairOSError::KeyValue(FairOSKeyValueError::Error)))
+                }
+            };
+            let key = res.keys.first()?.clone();
+            let value = base64::decode(&res.values)
+                .ok()
+                .and_then(|value| String::from_utf8(value).ok())
+                .ok_or(FairOSError::KeyValue(FairOSKeyValueError::InvalidValue));
+            Some(value.map(|value| (key, value)))
+        })
+    }
 }
 
 impl Stream for KeyValueSeek<'_> {
-    type Item = (String, String);
+    type Item = KvSeekItem;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut query = HashMap::new();
-        query.insert("pod_name", self.pod.as_str());
-        query.insert("table_name", self.store.as_str());
-        let cookie = self.client.cookie(&self.username).unwrap();
-        let mut req = self
-            .client
-            .get::<KvEntryGetResponse>("/kv/seek/next", query, Some(cookie));
-        match unsafe { Pin::new_unchecked(&mut req) }.poll(cx) {
-            Poll::Ready(res) => match res {
-                Ok(res) => {
-                    let key = res.keys.get(0).unwrap().clone();
-                    Poll::Ready(Some((key, res.values)))
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(limit) = self.limit {
+            if self.count >= limit {
+                return Poll::Ready(None);
+            }
+        }
+        if self.next.is_none() {
+            let next = self.seek_next();
+            self.next = Some(next);
+        }
+        match self.next.as_mut().unwrap().as_mut().poll(cx) {
+            Poll::Ready(item) => {
+                self.next = None;
+                match item {
+                    Some(Ok(_)) => self.count += 1,
+                    // The seek cannot continue after an error, so the stream ends with it.
+                    _ => self.limit = Some(self.count),
                 }
-                Err(_) => Poll::Ready(None),
-            },
+                Poll::Ready(item)
+            }
             Poll::Pending => Poll::Pending,
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         if let Some(limit) = self.limit {
-            (0, Some(limit as usize))
+            (0, Some((limit - self.count) as usize))
         } else {
             (0, None)
         }
@@ -105,6 +161,7 @@ impl Client {
         name: &str,
         index_type: IndexType,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let index_type = match index_type {
             IndexType::Str => "string",
             IndexType::Number => "number",
@@ -134,6 +191,7 @@ impl Client {
         pod: &str,
         name: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": name,
@@ -158,6 +216,7 @@ impl Client {
         pod: &str,
         name: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": name,
@@ -181,6 +240,7 @@ impl Client {
         username: &str,
         pod: &str,
     ) -> Result<Vec<KeyValueStore>, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         let cookie = self.cookie(username).unwrap();
@@ -211,11 +271,24 @@ impl Client {
         key: &str,
         value: T,
     ) -> Result<(), FairOSError> {
+        let value = serde_json::to_string(&value).unwrap();
+        self.put_kv_raw(username, pod, store, key, &value).await
+    }
+
+    pub(crate) async fn put_kv_raw(
+        &self,
+        username: &str,
+        pod: &str,
+        store: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": store,
             "key": key,
-            "value": serde_json::to_string(&value).unwrap(),
+            "value": value,
         })
         .to_string()
         .as_bytes()
@@ -238,6 +311,7 @@ impl Client {
         store: &str,
         key: &str,
     ) -> Result<T, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("table_name", store);
@@ -261,6 +335,7 @@ impl Client {
         store: &str,
         key: &str,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": store,
@@ -286,6 +361,7 @@ impl Client {
         pod: &str,
         store: &str,
     ) -> Result<u32, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": store,
@@ -311,6 +387,7 @@ impl Client {
         store: &str,
         key: &str,
     ) -> Result<bool, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut query = HashMap::new();
         query.insert("pod_name", pod);
         query.insert("table_name", store);
@@ -334,6 +411,7 @@ impl Client {
         buffer: R,
         memory: bool,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("table_name", store);
@@ -365,6 +443,7 @@ impl Client {
         local_path: P,
         memory: bool,
     ) -> Result<(), FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let mut multipart = Multipart::new();
         multipart.add_text("pod_name", pod);
         multipart.add_text("table_name", store);
@@ -388,7 +467,7 @@ impl Client {
         Ok(())
     }
 
-    pub(crate) async fn _kv_seek(
+    pub async fn kv_seek(
         &self,
         username: &str,
         pod: &str,
@@ -397,6 +476,7 @@ impl Client {
         end_key: Option<&str>,
         limit: Option<u32>,
     ) -> Result<KeyValueSeek<'_>, FairOSError> {
+        self.ensure_pod_open(username, pod).await?;
         let data = json!({
             "pod_name": pod,
             "table_name": store,
@@ -416,11 +496,13 @@ impl Client {
                 RequestError::Message(_) => FairOSError::KeyValue(FairOSKeyValueError::Error),
             })?;
         Ok(KeyValueSeek {
-            client: &self,
+            client: self,
             username: username.into(),
             pod: pod.into(),
             store: store.into(),
             limit,
+            count: 0,
+            next: None,
         })
     }
 }
@@ -432,6 +514,7 @@ mod tests {
         distributions::{Alphanumeric, Uniform},
         thread_rng, Rng,
     };
+    use secrecy::SecretString;
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, PartialEq, Deserialize, Serialize)]
@@ -448,12 +531,13 @@ mod tests {
             .collect()
     }
 
-    fn random_password() -> String {
-        thread_rng()
+    fn random_password() -> SecretString {
+        let password = thread_rng()
             .sample_iter(Uniform::new_inclusive(0, 255))
             .take(8)
             .map(char::from)
-            .collect()
+            .collect::<String>();
+        SecretString::from(password)
     }
 
     #[tokio::test]
@@ -767,9 +851,9 @@ mod tests {
     //         .kv_seek(&username, &pod, "table", "bcd", None, None)
     //         .await;
     //     assert!(res.is_ok());
-    //     let pairs = res.unwrap().collect::<Vec<(String, String)>>().await;
+    //     let pairs = res.unwrap().try_collect::<Vec<(String, String)>>().await;
     //     assert_eq!(
-    //         pairs,
+    //         pairs.unwrap(),
     //         vec![
     //             ("bcd".into(), "efg".into()),
     //             ("cde".into(), "fgh".into()),
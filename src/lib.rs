@@ -1,17 +1,50 @@
+mod account;
+mod backup;
 mod client;
+mod diff;
 mod doc;
 mod error;
 mod filesystem;
+mod find;
+mod keystore;
 mod kv;
 mod pod;
+mod policy;
+mod rename;
+mod runtime;
+mod sharing;
+mod sync;
+mod transfer;
+mod usage;
 mod user;
+mod walk;
+mod watch;
 
-pub use client::Client;
+pub use account::AccountManager;
+pub use backup::{
+    BackupDocumentDatabase, BackupFile, BackupKeyValueStore, BackupManifest, RestoreReport,
+};
+pub use bip39::Language;
+pub use client::{Client, RetryPolicy};
+pub use diff::{DiffChange, DiffEntry, DiffReason, DirDiff};
 pub use doc::{DocumentDatabase, Expr, ExprValue, FieldType};
-pub use error::{FairOSError, FairOSPodError, FairOSUserError};
+pub use error::{
+    AccountRecovery, FairOSBackupError, FairOSError, FairOSKeystoreError, FairOSPodError,
+    FairOSSharingError, FairOSTransferError, FairOSUserError,
+};
 pub use filesystem::{
-    BlockSize, Compression, DirEntry, DirInfo, FileBlock, FileEntry, FileInfo, SharedFileInfo,
+    BlockSize, Compression, DirEntry, DirInfo, FileBlock, FileEntry, FileInfo, RemoveReport,
+    SharedFileInfo,
 };
-pub use kv::{IndexType, KeyValueStore};
+pub use find::FindQuery;
+pub use keystore::{Credentials, Keystore};
+pub use kv::{IndexType, KeyValueSeek, KeyValueStore};
 pub use pod::{PodInfo, SharedPodInfo};
-pub use user::{UserExport, UserInfo};
+pub use policy::{PasswordPolicy, UsernamePolicy};
+pub use sharing::{SharedResource, SharingReference};
+pub use sync::{SyncDirection, SyncReport, SYNC_STATE_FILE};
+pub use transfer::{CopyItem, CopyProgress, TransferReport, UploadOptions, UploadRule};
+pub use usage::{DirUsage, PodUsage};
+pub use user::{UserExport, UserInfo, WordCount};
+pub use walk::{WalkEntry, WalkEntryKind, WalkOrder};
+pub use watch::WatchEvent;
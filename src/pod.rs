@@ -1,13 +1,20 @@
 use crate::{
-    client::{MessageResponse, RequestError},
-    Client, FairOSError, FairOSPodError,
+    client::{secret_body, MessageResponse, RequestError},
+    Client, FairOSError, FairOSPodError, SharingReference,
 };
 
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+#[derive(Serialize)]
+struct PodPasswordRequest<'a> {
+    pod_name: &'a str,
+    password: &'a str,
+}
+
 #[derive(Debug, Deserialize)]
 struct PodShareResponse {
     pod_sharing_reference: String,
@@ -39,6 +46,19 @@ struct PodReceiveInfoResponse {
     shared_time: String,
 }
 
+pub trait PasswordProvider: Send + Sync {
+    fn pod_password(&self, username: &str, pod: &str) -> Option<SecretString>;
+}
+
+impl<F> PasswordProvider for F
+where
+    F: Fn(&str, &str) -> Option<SecretString> + Send + Sync,
+{
+    fn pod_password(&self, username: &str, pod: &str) -> Option<SecretString> {
+        self(username, pod)
+    }
+}
+
 #[derive(Debug)]
 pub struct PodInfo {
     pub name: String,
@@ -59,15 +79,12 @@ impl Client {
         &self,
         username: &str,
         name: &str,
-        password: &str,
+        password: &SecretString,
     ) -> Result<(), FairOSError> {
-        let data = json!({
-            "pod_name": name,
-            "password": password,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        let data = secret_body(&PodPasswordRequest {
+            pod_name: name,
+            password: password.expose_secret(),
+        });
         let cookie = self.cookie(username).unwrap();
         let _ = self
             .post::<MessageResponse>("/pod/new", data, Some(cookie))
@@ -76,6 +93,7 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Pod(FairOSPodError::Error),
             })?;
+        self.set_pod_open(username, name, true);
         Ok(())
     }
 
@@ -83,15 +101,12 @@ impl Client {
         &self,
         username: &str,
         name: &str,
-        password: &str,
+        password: &SecretString,
     ) -> Result<(), FairOSError> {
-        let data = json!({
-            "pod_name": name,
-            "password": password,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        let data = secret_body(&PodPasswordRequest {
+            pod_name: name,
+            password: password.expose_secret(),
+        });
         let cookie = self.cookie(username).unwrap();
         let _ = self
             .post::<MessageResponse>("/pod/open", data, Some(cookie))
@@ -100,6 +115,7 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Pod(FairOSPodError::Error),
             })?;
+        self.set_pod_open(username, name, true);
         Ok(())
     }
 
@@ -126,6 +142,7 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Pod(FairOSPodError::Error),
             })?;
+        self.set_pod_open(username, name, false);
         Ok(())
     }
 
@@ -133,15 +150,12 @@ impl Client {
         &self,
         username: &str,
         name: &str,
-        password: &str,
-    ) -> Result<String, FairOSError> {
-        let data = json!({
-            "pod_name": name,
-            "password": password,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        password: &SecretString,
+    ) -> Result<SharingReference, FairOSError> {
+        let data = secret_body(&PodPasswordRequest {
+            pod_name: name,
+            password: password.expose_secret(),
+        });
         let cookie = self.cookie(username).unwrap();
         let (res, _) = self
             .post::<PodShareResponse>("/pod/share", data, Some(cookie))
@@ -150,22 +164,19 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Pod(FairOSPodError::Error),
             })?;
-        Ok(res.pod_sharing_reference)
+        res.pod_sharing_reference.parse()
     }
 
     pub async fn delete_pod(
         &self,
         username: &str,
         name: &str,
-        password: &str,
+        password: &SecretString,
     ) -> Result<(), FairOSError> {
-        let data = json!({
-            "pod_name": name,
-            "password": password,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        let data = secret_body(&PodPasswordRequest {
+            pod_name: name,
+            password: password.expose_secret(),
+        });
         let cookie = self.cookie(username).unwrap();
         let _: MessageResponse = self
             .delete("/pod/delete", data, cookie)
@@ -174,6 +185,7 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::Pod(FairOSPodError::Error),
             })?;
+        self.set_pod_open(username, name, false);
         Ok(())
     }
 
@@ -230,10 +242,10 @@ impl Client {
     pub async fn receive_shared_pod(
         &self,
         username: &str,
-        reference: &str,
+        reference: &SharingReference,
     ) -> Result<(), FairOSError> {
         let mut query = HashMap::new();
-        query.insert("sharing_ref", reference);
+        query.insert("sharing_ref", reference.as_str());
         let cookie = self.cookie(username).unwrap();
         let _: MessageResponse = self
             .get("/pod/receive", query, Some(cookie))
@@ -248,10 +260,10 @@ impl Client {
     pub async fn shared_pod_info(
         &self,
         username: &str,
-        reference: &str,
+        reference: &SharingReference,
     ) -> Result<SharedPodInfo, FairOSError> {
         let mut query = HashMap::new();
-        query.insert("sharing_ref", reference);
+        query.insert("sharing_ref", reference.as_str());
         let cookie = self.cookie(username).unwrap();
         let res: PodReceiveInfoResponse = self
             .get("/pod/receiveinfo", query, Some(cookie))
@@ -268,6 +280,26 @@ impl Client {
             shared_time: res.shared_time,
         })
     }
+
+    // Opens a pod with the password from the password provider the first time a pod
+    // scoped call needs it. Without a provider the call is left to fail as before.
+    pub(crate) async fn ensure_pod_open(
+        &self,
+        username: &str,
+        pod: &str,
+    ) -> Result<(), FairOSError> {
+        let provider = match self.password_provider() {
+            Some(provider) => provider,
+            None => return Ok(()),
+        };
+        if self.is_pod_open(username, pod) {
+            return Ok(());
+        }
+        match provider.pod_password(username, pod) {
+            Some(password) => self.open_pod(username, pod, &password).await,
+            None => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -277,6 +309,7 @@ mod tests {
         distributions::{Alphanumeric, Uniform},
         thread_rng, Rng,
     };
+    use secrecy::SecretString;
 
     fn random_name() -> String {
         thread_rng()
@@ -286,12 +319,13 @@ mod tests {
             .collect()
     }
 
-    fn random_password() -> String {
-        thread_rng()
+    fn random_password() -> SecretString {
+        let password = thread_rng()
             .sample_iter(Uniform::new_inclusive(0, 255))
             .take(8)
             .map(char::from)
-            .collect()
+            .collect::<String>();
+        SecretString::from(password)
     }
 
     #[tokio::test]
@@ -491,4 +525,61 @@ mod tests {
         assert_eq!(info.username, username1);
         // assert_eq!(info.user_address, address);
     }
+
+    #[tokio::test]
+    async fn test_open_pods_tracking() {
+        let fairos = Client::new();
+        fairos.set_pod_open("username", "pod2", true);
+        fairos.set_pod_open("username", "pod1", true);
+        assert!(fairos.is_pod_open("username", "pod1"));
+        assert_eq!(fairos.open_pods("username"), vec!["pod1", "pod2"]);
+        fairos.set_pod_open("username", "pod2", false);
+        assert!(!fairos.is_pod_open("username", "pod2"));
+        assert_eq!(fairos.take_open_pods("username"), vec!["pod1"]);
+        assert!(fairos.open_pods("username").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ensure_pod_open_without_password_succeeds() {
+        let mut fairos = Client::new();
+        let res = fairos.ensure_pod_open("username", "pod").await;
+        assert!(res.is_ok());
+        fairos.set_password_provider(Some(Box::new(|_: &str, _: &str| None)));
+        let res = fairos.ensure_pod_open("username", "pod").await;
+        assert!(res.is_ok());
+        assert!(!fairos.is_pod_open("username", "pod"));
+    }
+
+    #[tokio::test]
+    async fn test_auto_open_pod_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod_name = random_name();
+        let res = fairos.create_pod(&username, &pod_name, &password).await;
+        assert!(res.is_ok());
+        assert!(fairos.is_pod_open(&username, &pod_name));
+        let res = fairos.close_pod(&username, &pod_name).await;
+        assert!(res.is_ok());
+        assert!(!fairos.is_pod_open(&username, &pod_name));
+        let provider_password = password.clone();
+        fairos.set_password_provider(Some(Box::new(move |_: &str, _: &str| {
+            Some(provider_password.clone())
+        })));
+        let res = fairos.mkdir(&username, &pod_name, "/Documents").await;
+        assert!(res.is_ok());
+        assert!(fairos.is_pod_open(&username, &pod_name));
+        let res = fairos.logout(&username).await;
+        assert!(res.is_ok());
+        assert!(fairos.open_pods(&username).is_empty());
+        fairos.set_pod_open(&username, &pod_name, true);
+        let res = fairos.login(&username, &password).await;
+        assert!(res.is_ok());
+        assert!(!fairos.is_pod_open(&username, &pod_name));
+        let res = fairos.mkdir(&username, &pod_name, "/Photos").await;
+        assert!(res.is_ok());
+        assert!(fairos.is_pod_open(&username, &pod_name));
+    }
 }
@@ -0,0 +1,219 @@
+use crate::error::{FairOSError, FairOSUserError};
+
+// Size of the pool assumed for characters outside of the ASCII classes.
+const OTHER_POOL_SIZE: f64 = 100.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub min_entropy: f64,
+}
+
+impl PasswordPolicy {
+    pub fn none() -> Self {
+        Self {
+            min_length: 0,
+            max_length: None,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            min_entropy: 0.0,
+        }
+    }
+
+    // Estimates the entropy in bits of a password from its length and the size of the
+    // character classes it draws from.
+    pub fn entropy(password: &str) -> f64 {
+        let mut pool = 0.0;
+        if password.chars().any(|c| c.is_ascii_lowercase()) {
+            pool += 26.0;
+        }
+        if password.chars().any(|c| c.is_ascii_uppercase()) {
+            pool += 26.0;
+        }
+        if password.chars().any(|c| c.is_ascii_digit()) {
+            pool += 10.0;
+        }
+        if password
+            .chars()
+            .any(|c| c.is_ascii_punctuation() || c == ' ')
+        {
+            pool += 33.0;
+        }
+        if !password.is_ascii() {
+            pool += OTHER_POOL_SIZE;
+        }
+        if pool == 0.0 {
+            return 0.0;
+        }
+        password.chars().count() as f64 * f64::log2(pool)
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), FairOSError> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(FairOSError::User(FairOSUserError::PasswordTooShort));
+        }
+        if let Some(max_length) = self.max_length {
+            if length > max_length {
+                return Err(FairOSError::User(FairOSUserError::PasswordTooLong));
+            }
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            return Err(FairOSError::User(FairOSUserError::PasswordMissingLowercase));
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            return Err(FairOSError::User(FairOSUserError::PasswordMissingUppercase));
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_numeric()) {
+            return Err(FairOSError::User(FairOSUserError::PasswordMissingDigit));
+        }
+        if self.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            return Err(FairOSError::User(FairOSUserError::PasswordMissingSymbol));
+        }
+        if Self::entropy(password) < self.min_entropy {
+            return Err(FairOSError::User(FairOSUserError::PasswordTooWeak));
+        }
+        Ok(())
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: Some(128),
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            min_entropy: 40.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsernamePolicy {
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    pub forbidden: Vec<String>,
+}
+
+impl UsernamePolicy {
+    pub fn none() -> Self {
+        Self {
+            min_length: 0,
+            max_length: None,
+            forbidden: Vec::new(),
+        }
+    }
+
+    pub fn validate(&self, username: &str) -> Result<(), FairOSError> {
+        let length = username.chars().count();
+        if length < self.min_length {
+            return Err(FairOSError::User(FairOSUserError::UsernameTooShort));
+        }
+        if let Some(max_length) = self.max_length {
+            if length > max_length {
+                return Err(FairOSError::User(FairOSUserError::UsernameTooLong));
+            }
+        }
+        if self
+            .forbidden
+            .iter()
+            .any(|forbidden| forbidden.to_lowercase() == username.to_lowercase())
+        {
+            return Err(FairOSError::User(FairOSUserError::UsernameForbidden));
+        }
+        Ok(())
+    }
+}
+
+impl Default for UsernamePolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 3,
+            max_length: Some(64),
+            forbidden: vec!["admin".into(), "root".into(), "fairos".into()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FairOSError, FairOSUserError, PasswordPolicy, UsernamePolicy};
+
+    #[tokio::test]
+    async fn test_password_policy_none_succeeds() {
+        let res = PasswordPolicy::none().validate("");
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_password_policy_default() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            policy.validate("aB3"),
+            Err(FairOSError::User(FairOSUserError::PasswordTooShort))
+        );
+        assert_eq!(
+            policy.validate("ABCDEFG3"),
+            Err(FairOSError::User(FairOSUserError::PasswordMissingLowercase))
+        );
+        assert_eq!(
+            policy.validate("abcdefg3"),
+            Err(FairOSError::User(FairOSUserError::PasswordMissingUppercase))
+        );
+        assert_eq!(
+            policy.validate("abcdefgH"),
+            Err(FairOSError::User(FairOSUserError::PasswordMissingDigit))
+        );
+        assert_eq!(
+            policy.validate(&format!("{}A1", "a".repeat(127))),
+            Err(FairOSError::User(FairOSUserError::PasswordTooLong))
+        );
+        assert!(policy.validate("correctHorse7battery").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_password_policy_symbol_and_entropy() {
+        let policy = PasswordPolicy {
+            require_symbol: true,
+            min_entropy: 60.0,
+            ..PasswordPolicy::none()
+        };
+        assert_eq!(
+            policy.validate("abcdefgh"),
+            Err(FairOSError::User(FairOSUserError::PasswordMissingSymbol))
+        );
+        assert_eq!(
+            policy.validate("abc!"),
+            Err(FairOSError::User(FairOSUserError::PasswordTooWeak))
+        );
+        assert!(policy.validate("Tr0ub4dor&3-horse").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_username_policy_default() {
+        let policy = UsernamePolicy::default();
+        assert_eq!(
+            policy.validate("ab"),
+            Err(FairOSError::User(FairOSUserError::UsernameTooShort))
+        );
+        assert_eq!(
+            policy.validate(&"a".repeat(65)),
+            Err(FairOSError::User(FairOSUserError::UsernameTooLong))
+        );
+        assert_eq!(
+            policy.validate("Admin"),
+            Err(FairOSError::User(FairOSUserError::UsernameForbidden))
+        );
+        assert!(policy.validate("alice").is_ok());
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{
+    diff::relative_path,
+    error::{FairOSError, FairOSFileSystemError, FairOSTransferError},
+    transfer::{join_path, parent_dir},
+    Client,
+};
+
+// Checks a move or copy before anything is touched. A directory cannot be moved or
+// copied into itself.
+pub(crate) fn validate_destination(
+    src_pod: &str,
+    src_path: &str,
+    dst_pod: &str,
+    dst_path: &str,
+) -> Result<(), FairOSError> {
+    let src_path = src_path.trim_end_matches('/');
+    let dst_path = dst_path.trim_end_matches('/');
+    if src_path.is_empty()
+        || dst_path.is_empty()
+        || (src_pod == dst_pod && dst_path.starts_with(&format!("{}/", src_path)))
+    {
+        return Err(FairOSError::Transfer(
+            FairOSTransferError::InvalidDestination,
+        ));
+    }
+    Ok(())
+}
+
+impl Client {
+    async fn file_exists(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+    ) -> Result<bool, FairOSError> {
+        Ok(self.file_entry(username, pod, path).await?.is_some())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn verify_copy(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        src_size: u32,
+        dst_pod: &str,
+        dst_path: &str,
+        verify_content: bool,
+    ) -> Result<bool, FairOSError> {
+        let info = self.file_info(username, dst_pod, dst_path).await?;
+        if info.size != src_size {
+            return Ok(false);
+        }
+        if verify_content {
+            let src_hash = self.content_hash(username, src_pod, src_path).await?;
+            let dst_hash = self.content_hash(username, dst_pod, dst_path).await?;
+            return Ok(src_hash == dst_hash);
+        }
+        Ok(true)
+    }
+
+    // Copies a file and verifies the copy, unless a copy with the same content is already
+    // at the destination from a move that was interrupted. Returns whether the file was
+    // copied.
+    #[allow(clippy::too_many_arguments)]
+    async fn copy_verified(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        content_type: &str,
+        dst_pod: &str,
+        dst_path: &str,
+        verify_content: bool,
+    ) -> Result<bool, FairOSError> {
+        if self.file_exists(username, dst_pod, dst_path).await? {
+            // An unrelated file can have the same size, so the content is always compared
+            // before the source is treated as already copied.
+            let info = self.file_info(username, src_pod, src_path).await?;
+            let verified = self
+                .verify_copy(
+                    username, src_pod, src_path, info.size, dst_pod, dst_path, true,
+                )
+                .await?;
+            if verified {
+                return Ok(false);
+            }
+            return Err(FairOSError::Transfer(
+                FairOSTransferError::DestinationExists,
+            ));
+        }
+        let info = self
+            .copy_file_to(
+                username,
+                src_pod,
+                src_path,
+                content_type,
+                self,
+                username,
+                dst_pod,
+                dst_path,
+            )
+            .await?;
+        let verified = self
+            .verify_copy(
+                username,
+                src_pod,
+                src_path,
+                info.size,
+                dst_pod,
+                dst_path,
+                verify_content,
+            )
+            .await;
+        if !matches!(verified, Ok(true)) {
+            if let Err(err) = self.rm(username, dst_pod, dst_path).await {
+                log::warn!("could not remove unverified copy {}: {:?}", dst_path, err);
+            }
+            verified?;
+            return Err(FairOSError::Transfer(
+                FairOSTransferError::VerificationFailed,
+            ));
+        }
+        Ok(true)
+    }
+
+    async fn mv_dir(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        dst_pod: &str,
+        dst_path: &str,
+        verify_content: bool,
+    ) -> Result<(), FairOSError> {
+        let (dirs, files) = self.list_tree(username, src_pod, src_path).await?;
+        let target = |path: &str| {
+            format!(
+                "{}{}",
+                dst_path.trim_end_matches('/'),
+                relative_path(src_path, path)
+            )
+        };
+        let mut created_dirs = Vec::new();
+        let mut copied_files = Vec::new();
+        let res = async {
+            let dst_dirs = Some(dst_path.trim_end_matches('/').to_string())
+                .into_iter()
+                .chain(dirs.iter().map(|dir| target(dir)));
+            for dir in dst_dirs {
+                if !self.dir_exists(username, dst_pod, &dir).await? {
+                    self.mkdir(username, dst_pod, &dir).await?;
+                    created_dirs.push(dir);
+                }
+            }
+            for (path, entry) in &files {
+                let dst_file = target(path);
+                let copied = self
+                    .copy_verified(
+                        username,
+                        src_pod,
+                        path,
+                        &entry.content_type,
+                        dst_pod,
+                        &dst_file,
+                        verify_content,
+                    )
+                    .await?;
+                if copied {
+                    copied_files.push(dst_file);
+                }
+            }
+            Ok::<_, FairOSError>(())
+        }
+        .await;
+
+        // Undo what this attempt created so the source is the only copy again.
+        if let Err(err) = res {
+            for path in copied_files {
+                if let Err(err) = self.rm(username, dst_pod, &path).await {
+                    log::warn!("could not roll back {}: {:?}", path, err);
+                }
+            }
+            for dir in created_dirs.into_iter().rev() {
+                if let Err(err) = self.rmdir(username, dst_pod, &dir).await {
+                    log::warn!("could not roll back {}: {:?}", dir, err);
+                }
+            }
+            return Err(err);
+        }
+
+        // Everything is copied and verified, so running the move again resumes from here
+        // if removing the source fails.
+        let report = self
+            .rmdir_recursive(username, src_pod, src_path, 1, false)
+            .await?;
+        match report.failures.into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn mv(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        dst_pod: &str,
+        dst_path: &str,
+        verify_content: bool,
+    ) -> Result<(), FairOSError> {
+        validate_destination(src_pod, src_path, dst_pod, dst_path)?;
+        if src_pod == dst_pod && src_path.trim_end_matches('/') == dst_path.trim_end_matches('/') {
+            return Ok(());
+        }
+        if self.dir_exists(username, src_pod, src_path).await? {
+            return self
+                .mv_dir(
+                    username,
+                    src_pod,
+                    src_path,
+                    dst_pod,
+                    dst_path,
+                    verify_content,
+                )
+                .await;
+        }
+        let entry = self
+            .file_entry(username, src_pod, src_path)
+            .await?
+            .ok_or(FairOSError::FileSystem(FairOSFileSystemError::Error))?;
+        self.copy_verified(
+            username,
+            src_pod,
+            src_path,
+            &entry.content_type,
+            dst_pod,
+            dst_path,
+            verify_content,
+        )
+        .await?;
+        self.rm(username, src_pod, src_path).await
+    }
+
+    pub async fn rename(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+        name: &str,
+        verify_content: bool,
+    ) -> Result<(), FairOSError> {
+        if name.is_empty() || name.contains('/') {
+            return Err(FairOSError::Transfer(
+                FairOSTransferError::InvalidDestination,
+            ));
+        }
+        let dst_path = join_path(parent_dir(path.trim_end_matches('/')), name);
+        self.mv(username, pod, path, pod, &dst_path, verify_content)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_destination, Client};
+    use crate::{
+        error::{FairOSError, FairOSTransferError},
+        BlockSize,
+    };
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    #[tokio::test]
+    async fn test_validate_destination() {
+        let res = validate_destination("pod", "/photos", "pod", "/photos/cats");
+        assert_eq!(
+            res,
+            Err(FairOSError::Transfer(
+                FairOSTransferError::InvalidDestination
+            ))
+        );
+        let res = validate_destination("pod", "/", "pod", "/photos");
+        assert_eq!(
+            res,
+            Err(FairOSError::Transfer(
+                FairOSTransferError::InvalidDestination
+            ))
+        );
+        assert!(validate_destination("pod", "/photos", "pod2", "/photos/cats").is_ok());
+        assert!(validate_destination("pod", "/photos", "pod", "/photos-old").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rename_invalid_name_fails() {
+        let fairos = Client::new();
+        let res = fairos
+            .rename("username", "pod", "/cat.txt", "photos/cat.txt", false)
+            .await;
+        assert_eq!(
+            res,
+            Err(FairOSError::Transfer(
+                FairOSTransferError::InvalidDestination
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mv_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let pod2 = random_name();
+        let res = fairos.create_pod(&username, &pod2, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                None,
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .rename(&username, &pod, "/photos/cat.txt", "kitten.txt", true)
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .mv(&username, &pod, "/photos", &pod2, "/cats", true)
+            .await;
+        assert!(res.is_ok());
+        let res = fairos.dir_exists(&username, &pod, "/photos").await;
+        assert!(!res.unwrap());
+        let res = fairos
+            .download_buffer(&username, &pod2, "/cats/kitten.txt")
+            .await;
+        assert_eq!(res.unwrap(), "meow".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_mv_onto_different_file_fails() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        for (name, content) in [("cat.txt", "meow"), ("dog.txt", "woof")] {
+            let res = fairos
+                .upload_buffer(
+                    &username,
+                    &pod,
+                    "/",
+                    name,
+                    content.as_bytes(),
+                    mime::TEXT_PLAIN,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await;
+            assert!(res.is_ok());
+        }
+        let res = fairos
+            .mv(&username, &pod, "/cat.txt", &pod, "/dog.txt", false)
+            .await;
+        assert_eq!(
+            res,
+            Err(FairOSError::Transfer(
+                FairOSTransferError::DestinationExists
+            ))
+        );
+        let res = fairos.download_buffer(&username, &pod, "/cat.txt").await;
+        assert_eq!(res.unwrap(), "meow".as_bytes());
+        let res = fairos.download_buffer(&username, &pod, "/dog.txt").await;
+        assert_eq!(res.unwrap(), "woof".as_bytes());
+    }
+}
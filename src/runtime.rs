@@ -0,0 +1,105 @@
+use core::{future::Future, pin::pin, time::Duration};
+
+use futures::future::{self, Either};
+
+#[cfg(not(any(
+    feature = "runtime-tokio",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+)))]
+compile_error!(
+    "one of the `runtime-tokio`, `runtime-async-std` or `runtime-smol` features must be enabled"
+);
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Elapsed;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Executor;
+
+impl<F> hyper::rt::Executor<F> for Executor
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        spawn(compat(async move {
+            fut.await;
+        }));
+    }
+}
+
+#[cfg(feature = "runtime-tokio")]
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(fut: F) {
+    tokio::spawn(fut);
+}
+
+#[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(fut: F) {
+    async_std::task::spawn(fut);
+}
+
+#[cfg(all(
+    feature = "runtime-smol",
+    not(any(feature = "runtime-tokio", feature = "runtime-async-std"))
+))]
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(fut: F) {
+    smol::spawn(fut).detach();
+}
+
+#[cfg(feature = "runtime-tokio")]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+pub(crate) async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await;
+}
+
+#[cfg(all(
+    feature = "runtime-smol",
+    not(any(feature = "runtime-tokio", feature = "runtime-async-std"))
+))]
+pub(crate) async fn sleep(duration: Duration) {
+    smol::Timer::after(duration).await;
+}
+
+// hyper's connector and connection pool need a tokio reactor, so outside of tokio
+// they are driven through a compatibility layer.
+#[cfg(feature = "runtime-tokio")]
+pub(crate) fn compat<F: Future>(fut: F) -> F {
+    fut
+}
+
+#[cfg(not(feature = "runtime-tokio"))]
+pub(crate) fn compat<F: Future>(fut: F) -> async_compat::Compat<F> {
+    async_compat::Compat::new(fut)
+}
+
+pub(crate) async fn timeout<F: Future>(duration: Duration, fut: F) -> Result<F::Output, Elapsed> {
+    let fut = pin!(fut);
+    let delay = pin!(sleep(duration));
+    match future::select(fut, delay).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sleep, timeout, Elapsed};
+    use core::time::Duration;
+
+    #[tokio::test]
+    async fn test_timeout_completes() {
+        let res = timeout(Duration::from_secs(1), async { 42 }).await;
+        assert_eq!(res, Ok(42));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_elapses() {
+        let res = timeout(Duration::from_millis(10), sleep(Duration::from_secs(1))).await;
+        assert_eq!(res, Err(Elapsed));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    error::{FairOSError, FairOSSharingError},
+    Client,
+};
+
+use core::{fmt, str::FromStr};
+
+const URI_SCHEME: &str = "fairos://";
+
+// A swarm reference is 32 bytes, or 64 bytes when it is encrypted.
+const REFERENCE_LENGTHS: [usize; 2] = [64, 128];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharingReference(String);
+
+impl SharingReference {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SharingReference {
+    type Err = FairOSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !REFERENCE_LENGTHS.contains(&s.len()) || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(FairOSError::Sharing(FairOSSharingError::InvalidReference));
+        }
+        Ok(Self(s.to_ascii_lowercase()))
+    }
+}
+
+impl fmt::Display for SharingReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for SharingReference {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SharedResource {
+    Pod(SharingReference),
+    File(SharingReference),
+}
+
+impl SharedResource {
+    pub fn reference(&self) -> &SharingReference {
+        match self {
+            SharedResource::Pod(reference) => reference,
+            SharedResource::File(reference) => reference,
+        }
+    }
+}
+
+impl FromStr for SharedResource {
+    type Err = FairOSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = s
+            .strip_prefix(URI_SCHEME)
+            .ok_or(FairOSError::Sharing(FairOSSharingError::InvalidUri))?;
+        match path.split_once('/') {
+            Some(("pod", reference)) => Ok(SharedResource::Pod(reference.parse()?)),
+            Some(("file", reference)) => Ok(SharedResource::File(reference.parse()?)),
+            _ => Err(FairOSError::Sharing(FairOSSharingError::InvalidUri)),
+        }
+    }
+}
+
+impl fmt::Display for SharedResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharedResource::Pod(reference) => write!(f, "{}pod/{}", URI_SCHEME, reference),
+            SharedResource::File(reference) => write!(f, "{}file/{}", URI_SCHEME, reference),
+        }
+    }
+}
+
+impl Client {
+    pub async fn receive_shared_file_uri(
+        &self,
+        username: &str,
+        uri: &str,
+        pod: &str,
+        dir: &str,
+    ) -> Result<String, FairOSError> {
+        match uri.parse::<SharedResource>()? {
+            SharedResource::File(reference) => {
+                self.receive_shared_file(username, pod, &reference, dir)
+                    .await
+            }
+            SharedResource::Pod(_) => {
+                Err(FairOSError::Sharing(FairOSSharingError::UnexpectedResource))
+            }
+        }
+    }
+
+    pub async fn receive_shared_pod_uri(
+        &self,
+        username: &str,
+        uri: &str,
+    ) -> Result<(), FairOSError> {
+        match uri.parse::<SharedResource>()? {
+            SharedResource::Pod(reference) => self.receive_shared_pod(username, &reference).await,
+            SharedResource::File(_) => {
+                Err(FairOSError::Sharing(FairOSSharingError::UnexpectedResource))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, FairOSError, FairOSSharingError, SharedResource, SharingReference};
+
+    const REFERENCE: &str = "8b2e8c4bbd2a3d0e5a1f7e3c9d6b4a2f1e0d9c8b7a6f5e4d3c2b1a0f9e8d7c6b";
+
+    #[tokio::test]
+    async fn test_parse_sharing_reference_succeeds() {
+        let res = REFERENCE.parse::<SharingReference>();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().as_str(), REFERENCE);
+        let res = REFERENCE.repeat(2).parse::<SharingReference>();
+        assert!(res.is_ok());
+        let res = REFERENCE.to_uppercase().parse::<SharingReference>();
+        assert_eq!(res.unwrap().as_str(), REFERENCE);
+    }
+
+    #[tokio::test]
+    async fn test_parse_sharing_reference_fails() {
+        let res = REFERENCE[1..].parse::<SharingReference>();
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::InvalidReference)
+        );
+        let res = REFERENCE.replace('8', "x").parse::<SharingReference>();
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::InvalidReference)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shared_resource_uri() {
+        let reference = REFERENCE.parse::<SharingReference>().unwrap();
+        let pod = SharedResource::Pod(reference.clone());
+        let file = SharedResource::File(reference);
+        assert_eq!(pod.to_string(), format!("fairos://pod/{}", REFERENCE));
+        assert_eq!(file.to_string(), format!("fairos://file/{}", REFERENCE));
+        assert_eq!(pod.to_string().parse::<SharedResource>().unwrap(), pod);
+        assert_eq!(file.to_string().parse::<SharedResource>().unwrap(), file);
+        let res = format!("https://pod/{}", REFERENCE).parse::<SharedResource>();
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::InvalidUri)
+        );
+        let res = format!("fairos://dir/{}", REFERENCE).parse::<SharedResource>();
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::InvalidUri)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receive_shared_uri_unexpected_resource_fails() {
+        let fairos = Client::new();
+        let reference = REFERENCE.parse::<SharingReference>().unwrap();
+        let pod = SharedResource::Pod(reference.clone()).to_string();
+        let file = SharedResource::File(reference).to_string();
+        let res = fairos
+            .receive_shared_file_uri("username", &pod, "pod", "/")
+            .await;
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::UnexpectedResource)
+        );
+        let res = fairos.receive_shared_pod_uri("username", &file).await;
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::Sharing(FairOSSharingError::UnexpectedResource)
+        );
+    }
+}
@@ -0,0 +1,674 @@
+use crate::{
+    diff::SnapshotEntry,
+    error::{FairOSError, FairOSTransferError},
+    transfer::{join_path, local_tree, parent_dir, UploadMatcher, UploadOptions},
+    BlockSize, Client,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    path::Path,
+};
+
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use uuid::Uuid;
+
+pub const SYNC_STATE_FILE: &str = ".fairos-sync.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncDirection {
+    Upload,
+    Download,
+    Both,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncReport {
+    pub uploaded: Vec<String>,
+    pub downloaded: Vec<String>,
+    pub deleted_remote: Vec<String>,
+    pub deleted_local: Vec<String>,
+    pub conflicts: Vec<String>,
+    pub failures: Vec<(String, FairOSError)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub(crate) struct SyncEntry {
+    pub size: u64,
+    pub local_modification_time: i64,
+    pub remote_modification_time: u64,
+}
+
+// What both sides looked like after the last sync, stored in the local directory.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct SyncState {
+    pub pod: String,
+    pub dir: String,
+    pub entries: BTreeMap<String, SyncEntry>,
+    pub conflicts: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SyncAction {
+    Upload(String),
+    Download(String),
+    DeleteRemote(String),
+    DeleteLocal(String),
+    // Both sides changed to the same size, so they are in sync if their contents match.
+    Compare(String),
+    // Both sides were deleted.
+    Forget(String),
+    Conflict(String),
+}
+
+impl SyncAction {
+    fn path(&self) -> &str {
+        match self {
+            SyncAction::Upload(path)
+            | SyncAction::Download(path)
+            | SyncAction::DeleteRemote(path)
+            | SyncAction::DeleteLocal(path)
+            | SyncAction::Compare(path)
+            | SyncAction::Forget(path)
+            | SyncAction::Conflict(path) => path,
+        }
+    }
+}
+
+// Local files by path with their size and modification time.
+pub(crate) type LocalFiles = BTreeMap<String, (u64, i64)>;
+
+// Remote files by path with their size and modification time.
+pub(crate) type RemoteFiles = BTreeMap<String, (u64, u64)>;
+
+pub(crate) fn plan_sync(
+    local: &LocalFiles,
+    remote: &RemoteFiles,
+    state: &SyncState,
+    direction: SyncDirection,
+    compare_content: bool,
+) -> Vec<SyncAction> {
+    let paths = local
+        .keys()
+        .chain(remote.keys())
+        .chain(state.entries.keys())
+        .collect::<BTreeSet<_>>();
+    let mut actions = Vec::new();
+    for path in paths {
+        let path = path.clone();
+        let local = local.get(&path);
+        let remote = remote.get(&path);
+        let base = state.entries.get(&path);
+        let local_changed = match (local, base) {
+            (Some((size, mtime)), Some(base)) => {
+                *size != base.size || *mtime != base.local_modification_time
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        let remote_changed = match (remote, base) {
+            (Some((size, mtime)), Some(base)) => {
+                *size != base.size || *mtime != base.remote_modification_time
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        let local_wins = match (local_changed, remote_changed) {
+            (false, false) => continue,
+            (true, true) => {
+                actions.push(match (local, remote) {
+                    (None, None) => SyncAction::Forget(path),
+                    // Without comparing content, files of the same size can still differ.
+                    (Some((local_size, _)), Some((remote_size, _)))
+                        if compare_content && local_size == remote_size =>
+                    {
+                        SyncAction::Compare(path)
+                    }
+                    _ => SyncAction::Conflict(path),
+                });
+                continue;
+            }
+            (true, false) => direction != SyncDirection::Download,
+            (false, true) => direction == SyncDirection::Upload,
+        };
+        // Only a file that was synced before can have been deleted. A new file on the side
+        // that is not synced from is left alone.
+        actions.push(match (local_wins, local.is_some(), remote.is_some()) {
+            (true, true, _) => SyncAction::Upload(path),
+            (false, _, true) => SyncAction::Download(path),
+            (true, false, true) | (false, true, false) if base.is_none() => continue,
+            (true, false, true) => SyncAction::DeleteRemote(path),
+            (false, true, false) => SyncAction::DeleteLocal(path),
+            (_, false, false) => SyncAction::Forget(path),
+        });
+    }
+    actions
+}
+
+fn local_file(path: &Path) -> Option<(u64, i64)> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let mtime = FileTime::from_last_modification_time(&metadata).unix_seconds();
+    Some((metadata.len(), mtime))
+}
+
+fn read_state(path: &Path) -> Result<Option<SyncState>, FairOSError> {
+    match fs::read(path) {
+        Ok(buf) => serde_json::from_slice(&buf)
+            .map(Some)
+            .map_err(|_| FairOSError::Transfer(FairOSTransferError::InvalidSyncState)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn write_state(path: &Path, state: &SyncState) -> Result<(), FairOSError> {
+    let buf = serde_json::to_vec_pretty(state).unwrap();
+    fs::write(path, buf).map_err(|_| FairOSError::Transfer(FairOSTransferError::Io))
+}
+
+impl Client {
+    async fn remote_files(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+    ) -> Result<(HashSet<String>, RemoteFiles), FairOSError> {
+        let mut dirs = HashSet::new();
+        let mut files = RemoteFiles::new();
+        for (path, entry) in self.snapshot(username, pod, dir).await? {
+            match entry {
+                SnapshotEntry::Dir => {
+                    dirs.insert(path);
+                }
+                SnapshotEntry::File {
+                    size,
+                    modification_time,
+                } => {
+                    files.insert(path, (size as u64, modification_time));
+                }
+            }
+        }
+        Ok((dirs, files))
+    }
+
+    // Creates a directory relative to the synced directory and any of its missing parents.
+    async fn ensure_remote_dir(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        path: &str,
+        remote_dirs: &mut HashSet<String>,
+    ) -> Result<(), FairOSError> {
+        let mut missing = Vec::new();
+        let mut path = path;
+        while path != "/" && !remote_dirs.contains(path) {
+            missing.push(path);
+            path = parent_dir(path);
+        }
+        for path in missing.into_iter().rev() {
+            self.mkdir(username, pod, &join_path(dir, &path[1..]))
+                .await?;
+            remote_dirs.insert(path.into());
+        }
+        Ok(())
+    }
+
+    // Keeps the remote file under another name until the new one is uploaded, with the
+    // block size and compression of the file it replaces.
+    async fn replace_remote_file(
+        &self,
+        username: &str,
+        pod: &str,
+        remote_path: &str,
+        local_file: &Path,
+    ) -> Result<(), FairOSError> {
+        let info = self.file_info(username, pod, remote_path).await?;
+        let name = remote_path.rsplit('/').next().unwrap();
+        let backup_name = format!(".{}.{}", name, Uuid::new_v4());
+        let backup_path = join_path(parent_dir(remote_path), &backup_name);
+        self.rename(username, pod, remote_path, &backup_name, false)
+            .await?;
+        let res = self
+            .upload_file(
+                username,
+                pod,
+                parent_dir(remote_path),
+                local_file,
+                info.block_size,
+                info.compression,
+            )
+            .await;
+        match res {
+            Ok(_) => {
+                if let Err(err) = self.rm(username, pod, &backup_path).await {
+                    log::warn!("could not remove {}: {:?}", backup_path, err);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                if let Err(err) = self.rename(username, pod, &backup_path, name, false).await {
+                    log::error!(
+                        "could not restore {} from {}: {:?}",
+                        remote_path,
+                        backup_path,
+                        err
+                    );
+                }
+                Err(err)
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn apply_sync_action(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        local_path: &Path,
+        action: &SyncAction,
+        remote_files: &RemoteFiles,
+        remote_dirs: &mut HashSet<String>,
+    ) -> Result<bool, FairOSError> {
+        let io_error = |_| FairOSError::Transfer(FairOSTransferError::Io);
+        match action {
+            SyncAction::Upload(path) => {
+                let remote_path = join_path(dir, &path[1..]);
+                self.ensure_remote_dir(username, pod, dir, parent_dir(path), remote_dirs)
+                    .await?;
+                if remote_files.contains_key(path) {
+                    self.replace_remote_file(
+                        username,
+                        pod,
+                        &remote_path,
+                        &local_path.join(&path[1..]),
+                    )
+                    .await?;
+                } else {
+                    self.upload_file(
+                        username,
+                        pod,
+                        parent_dir(&remote_path),
+                        local_path.join(&path[1..]),
+                        BlockSize::Megabytes(1),
+                        None,
+                    )
+                    .await?;
+                }
+            }
+            SyncAction::Download(path) => {
+                let local_file = local_path.join(&path[1..]);
+                if let Some(parent) = local_file.parent() {
+                    fs::create_dir_all(parent).map_err(io_error)?;
+                }
+                self.download_file(username, pod, &join_path(dir, &path[1..]), &local_file)
+                    .await?;
+                let (_, mtime) = remote_files[path];
+                filetime::set_file_mtime(&local_file, FileTime::from_unix_time(mtime as i64, 0))
+                    .map_err(io_error)?;
+            }
+            SyncAction::DeleteRemote(path) => {
+                self.rm(username, pod, &join_path(dir, &path[1..])).await?;
+            }
+            SyncAction::DeleteLocal(path) => {
+                fs::remove_file(local_path.join(&path[1..])).map_err(io_error)?;
+            }
+            SyncAction::Compare(path) => {
+                let buf = fs::read(local_path.join(&path[1..])).map_err(io_error)?;
+                let local_hash = Keccak256::digest(&buf).to_vec();
+                let remote_hash = self
+                    .content_hash(username, pod, &join_path(dir, &path[1..]))
+                    .await?;
+                return Ok(local_hash == remote_hash);
+            }
+            SyncAction::Forget(_) => {}
+            SyncAction::Conflict(_) => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub async fn sync_dir<P: AsRef<Path>>(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        local_path: P,
+        direction: SyncDirection,
+        compare_content: bool,
+    ) -> Result<SyncReport, FairOSError> {
+        let local_path = local_path.as_ref();
+        let io_error = |_| FairOSError::Transfer(FairOSTransferError::Io);
+        fs::create_dir_all(local_path).map_err(io_error)?;
+        let state_path = local_path.join(SYNC_STATE_FILE);
+        // State recorded against another pod or directory says nothing about this one.
+        let mut state = read_state(&state_path)?
+            .filter(|state| state.pod == pod && state.dir == dir)
+            .unwrap_or_else(|| SyncState {
+                pod: pod.into(),
+                dir: dir.into(),
+                ..Default::default()
+            });
+
+        let matcher = UploadMatcher::new(&UploadOptions {
+            exclude: vec![SYNC_STATE_FILE.into()],
+            ..Default::default()
+        })?;
+        let (local_dirs, local_paths) = local_tree(local_path, &matcher)?;
+        let mut local = LocalFiles::new();
+        for path in local_paths {
+            if let Some(file) = local_file(&local_path.join(&path[1..])) {
+                local.insert(path, file);
+            }
+        }
+        let (mut remote_dirs, remote) = self.remote_files(username, pod, dir).await?;
+        let mut report = SyncReport::default();
+
+        // Directories are created on the other side but never deleted.
+        if direction != SyncDirection::Download {
+            for path in &local_dirs {
+                if let Err(err) = self
+                    .ensure_remote_dir(username, pod, dir, path, &mut remote_dirs)
+                    .await
+                {
+                    report.failures.push((path.clone(), err));
+                }
+            }
+        }
+        if direction != SyncDirection::Upload {
+            let local_dirs = local_dirs.into_iter().collect::<HashSet<_>>();
+            for path in remote_dirs
+                .iter()
+                .filter(|path| !local_dirs.contains(*path))
+            {
+                if fs::create_dir_all(local_path.join(&path[1..])).is_err() {
+                    report
+                        .failures
+                        .push((path.clone(), FairOSError::Transfer(FairOSTransferError::Io)));
+                }
+            }
+        }
+
+        let actions = plan_sync(&local, &remote, &state, direction, compare_content);
+        let mut settled = Vec::new();
+        for action in actions {
+            let res = self
+                .apply_sync_action(
+                    username,
+                    pod,
+                    dir,
+                    local_path,
+                    &action,
+                    &remote,
+                    &mut remote_dirs,
+                )
+                .await;
+            let in_sync = match res {
+                Ok(in_sync) => in_sync,
+                Err(err) => {
+                    report.failures.push((action.path().into(), err));
+                    continue;
+                }
+            };
+            match &action {
+                SyncAction::Upload(path) => report.uploaded.push(path.clone()),
+                SyncAction::Download(path) => report.downloaded.push(path.clone()),
+                SyncAction::DeleteRemote(path) => report.deleted_remote.push(path.clone()),
+                SyncAction::DeleteLocal(path) => report.deleted_local.push(path.clone()),
+                _ => {}
+            }
+            let path = action.path();
+            if in_sync {
+                state.conflicts.remove(path);
+                settled.push(path.to_string());
+            } else {
+                state.conflicts.insert(path.to_string());
+            }
+        }
+
+        // Record what both sides look like now for every path that was brought in sync,
+        // keeping the previous state of conflicts and failures so they are retried.
+        if !settled.is_empty() {
+            let (_, remote) = self.remote_files(username, pod, dir).await?;
+            for path in settled {
+                match (local_file(&local_path.join(&path[1..])), remote.get(&path)) {
+                    (Some((size, local_mtime)), Some((_, remote_mtime))) => {
+                        state.entries.insert(
+                            path,
+                            SyncEntry {
+                                size,
+                                local_modification_time: local_mtime,
+                                remote_modification_time: *remote_mtime,
+                            },
+                        );
+                    }
+                    _ => {
+                        state.entries.remove(&path);
+                    }
+                }
+            }
+        }
+        report.conflicts = state.conflicts.iter().cloned().collect();
+        write_state(&state_path, &state)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        plan_sync, Client, LocalFiles, RemoteFiles, SyncAction, SyncDirection, SyncEntry,
+        SyncState, SYNC_STATE_FILE,
+    };
+    use crate::{BlockSize, Compression};
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+    use std::fs;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn test_state() -> SyncState {
+        let mut state = SyncState::default();
+        for path in [
+            "/same",
+            "/local",
+            "/remote",
+            "/both",
+            "/gone-local",
+            "/gone-remote",
+        ] {
+            state.entries.insert(
+                path.into(),
+                SyncEntry {
+                    size: 4,
+                    local_modification_time: 1,
+                    remote_modification_time: 2,
+                },
+            );
+        }
+        state
+    }
+
+    fn test_files() -> (LocalFiles, RemoteFiles) {
+        let mut local = LocalFiles::new();
+        local.insert("/same".into(), (4, 1));
+        local.insert("/local".into(), (5, 3));
+        local.insert("/remote".into(), (4, 1));
+        local.insert("/both".into(), (5, 3));
+        local.insert("/gone-remote".into(), (4, 1));
+        local.insert("/new-local".into(), (4, 3));
+        let mut remote = RemoteFiles::new();
+        remote.insert("/same".into(), (4, 2));
+        remote.insert("/local".into(), (4, 2));
+        remote.insert("/remote".into(), (6, 4));
+        remote.insert("/both".into(), (6, 4));
+        remote.insert("/gone-local".into(), (4, 2));
+        remote.insert("/new-remote".into(), (4, 4));
+        (local, remote)
+    }
+
+    #[tokio::test]
+    async fn test_plan_sync_both() {
+        let (local, remote) = test_files();
+        let actions = plan_sync(&local, &remote, &test_state(), SyncDirection::Both, false);
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Conflict("/both".into()),
+                SyncAction::DeleteRemote("/gone-local".into()),
+                SyncAction::DeleteLocal("/gone-remote".into()),
+                SyncAction::Upload("/local".into()),
+                SyncAction::Upload("/new-local".into()),
+                SyncAction::Download("/new-remote".into()),
+                SyncAction::Download("/remote".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_sync_one_way() {
+        let (local, remote) = test_files();
+        let actions = plan_sync(&local, &remote, &test_state(), SyncDirection::Upload, false);
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Conflict("/both".into()),
+                SyncAction::DeleteRemote("/gone-local".into()),
+                SyncAction::Upload("/gone-remote".into()),
+                SyncAction::Upload("/local".into()),
+                SyncAction::Upload("/new-local".into()),
+                SyncAction::Upload("/remote".into()),
+            ]
+        );
+        let actions = plan_sync(
+            &local,
+            &remote,
+            &test_state(),
+            SyncDirection::Download,
+            false,
+        );
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Conflict("/both".into()),
+                SyncAction::Download("/gone-local".into()),
+                SyncAction::DeleteLocal("/gone-remote".into()),
+                SyncAction::Download("/local".into()),
+                SyncAction::Download("/new-remote".into()),
+                SyncAction::Download("/remote".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_sync_without_state() {
+        let (local, remote) = test_files();
+        let state = SyncState::default();
+        let actions = plan_sync(&local, &remote, &state, SyncDirection::Both, true);
+        assert_eq!(actions[0], SyncAction::Conflict("/both".into()));
+        assert!(actions.contains(&SyncAction::Compare("/same".into())));
+        let actions = plan_sync(&local, &remote, &state, SyncDirection::Both, false);
+        assert!(actions.contains(&SyncAction::Conflict("/same".into())));
+    }
+
+    #[tokio::test]
+    async fn test_sync_dir_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let root = random_name();
+        fs::create_dir_all(format!("{}/photos", root)).unwrap();
+        fs::write(format!("{}/photos/cat.txt", root), "meow").unwrap();
+        let res = fairos
+            .sync_dir(&username, &pod, "/", &root, SyncDirection::Both, true)
+            .await;
+        let res2 = fairos
+            .sync_dir(&username, &pod, "/", &root, SyncDirection::Both, true)
+            .await;
+        let state = fs::read_to_string(format!("{}/{}", root, SYNC_STATE_FILE));
+        fs::remove_dir_all(&root).unwrap();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().uploaded, vec!["/photos/cat.txt"]);
+        assert!(res2.is_ok());
+        let report = res2.unwrap();
+        assert!(report.uploaded.is_empty());
+        assert!(report.downloaded.is_empty());
+        assert!(state.unwrap().contains("/photos/cat.txt"));
+        let res = fairos
+            .download_buffer(&username, &pod, "/photos/cat.txt")
+            .await;
+        assert_eq!(res.unwrap(), "meow".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_sync_dir_replace_keeps_settings() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(2),
+                Some(Compression::Gzip),
+            )
+            .await;
+        assert!(res.is_ok());
+        let root = random_name();
+        let res = fairos
+            .sync_dir(&username, &pod, "/", &root, SyncDirection::Download, false)
+            .await;
+        fs::write(format!("{}/cat.txt", root), "meow meow").unwrap();
+        let res2 = fairos
+            .sync_dir(&username, &pod, "/", &root, SyncDirection::Upload, false)
+            .await;
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(res.unwrap().downloaded, vec!["/cat.txt"]);
+        assert_eq!(res2.unwrap().uploaded, vec!["/cat.txt"]);
+        let res = fairos.file_info(&username, &pod, "/cat.txt").await;
+        let info = res.unwrap();
+        assert_eq!(info.block_size, BlockSize::Kilobytes(2));
+        assert_eq!(info.compression, Some(Compression::Gzip));
+        let res = fairos.download_buffer(&username, &pod, "/cat.txt").await;
+        assert_eq!(res.unwrap(), "meow meow".as_bytes());
+        let res = fairos.ls(&username, &pod, "/").await;
+        assert_eq!(res.unwrap().1.len(), 1);
+    }
+}
@@ -0,0 +1,1082 @@
+use crate::{
+    diff::relative_path,
+    doc::indexed_fields,
+    error::{FairOSError, FairOSFileSystemError, FairOSTransferError, FairOSUserError},
+    rename::validate_destination,
+    BlockSize, Client, Compression, Expr, FileEntry, FileInfo, WalkEntryKind, WalkOrder,
+};
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    path::Path,
+};
+
+use filetime::FileTime;
+use futures::{stream, StreamExt, TryStreamExt};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use mime::Mime;
+use secrecy::{ExposeSecret, SecretString};
+use uuid::Uuid;
+
+const MIGRATION_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyItem {
+    Dir(String),
+    File(String),
+    KeyValueStore(String),
+    DocumentDatabase(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyProgress {
+    pub item: CopyItem,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadRule {
+    pub pattern: String,
+    pub block_size: BlockSize,
+    pub compression: Option<Compression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadOptions {
+    pub concurrency: usize,
+    pub block_size: BlockSize,
+    pub compression: Option<Compression>,
+    pub rules: Vec<UploadRule>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            block_size: BlockSize::Megabytes(1),
+            compression: None,
+            rules: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TransferReport {
+    pub dirs: Vec<String>,
+    pub files: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failures: Vec<(String, FairOSError)>,
+}
+
+pub(crate) fn join_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+pub(crate) fn parent_dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, FairOSError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|_| FairOSError::Transfer(FairOSTransferError::InvalidPattern))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|_| FairOSError::Transfer(FairOSTransferError::InvalidPattern))
+}
+
+// Patterns are matched against paths relative to the directory being transferred,
+// without a leading slash.
+pub(crate) struct UploadMatcher {
+    include: GlobSet,
+    exclude: GlobSet,
+    rules: Vec<(GlobMatcher, BlockSize, Option<Compression>)>,
+    block_size: BlockSize,
+    compression: Option<Compression>,
+}
+
+impl UploadMatcher {
+    pub(crate) fn new(options: &UploadOptions) -> Result<Self, FairOSError> {
+        let mut rules = Vec::new();
+        for rule in &options.rules {
+            let glob = Glob::new(&rule.pattern)
+                .map_err(|_| FairOSError::Transfer(FairOSTransferError::InvalidPattern))?;
+            rules.push((glob.compile_matcher(), rule.block_size, rule.compression));
+        }
+        Ok(Self {
+            include: glob_set(&options.include)?,
+            exclude: glob_set(&options.exclude)?,
+            rules,
+            block_size: options.block_size,
+            compression: options.compression,
+        })
+    }
+
+    fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.is_match(path.trim_start_matches('/'))
+    }
+
+    fn is_included(&self, path: &str) -> bool {
+        !self.is_excluded(path)
+            && (self.include.is_empty() || self.include.is_match(path.trim_start_matches('/')))
+    }
+
+    fn file_settings(&self, path: &str) -> (BlockSize, Option<Compression>) {
+        self.rules
+            .iter()
+            .find(|(matcher, _, _)| matcher.is_match(path.trim_start_matches('/')))
+            .map(|(_, block_size, compression)| (*block_size, *compression))
+            .unwrap_or((self.block_size, self.compression))
+    }
+}
+
+// Whether a local file has the size and modification time of a remote file, in which
+// case downloading it again can be skipped.
+fn is_unchanged(path: &Path, entry: &FileEntry) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            metadata.is_file()
+                && metadata.len() == entry.size as u64
+                && FileTime::from_last_modification_time(&metadata).unix_seconds()
+                    == entry.modification_time as i64
+        }
+        Err(_) => false,
+    }
+}
+
+// Lists the directories and files below a local directory as paths relative to it,
+// with parent directories before their children.
+pub(crate) fn local_tree(
+    root: &Path,
+    matcher: &UploadMatcher,
+) -> Result<(Vec<String>, Vec<String>), FairOSError> {
+    let io_error = |_| FairOSError::Transfer(FairOSTransferError::Io);
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut pending = vec!["/".to_string()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(root.join(dir.trim_start_matches('/')))
+            .map_err(io_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_error)?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(name) => {
+                    log::warn!("skipping {:?} which is not valid unicode", name);
+                    continue;
+                }
+            };
+            let path = join_path(&dir, &name);
+            if entry.file_type().map_err(io_error)?.is_dir() {
+                if !matcher.is_excluded(&path) {
+                    dirs.push(path.clone());
+                    pending.push(path);
+                }
+            } else if entry.path().is_file() && matcher.is_included(&path) {
+                files.push(path);
+            }
+        }
+    }
+    Ok((dirs, files))
+}
+
+impl Client {
+    // Lists every directory and file below a directory of an open pod, with parent
+    // directories listed before their children.
+    pub(crate) async fn list_tree(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+    ) -> Result<(Vec<String>, Vec<(String, FileEntry)>), FairOSError> {
+        let entries = self
+            .walk(username, pod, dir, WalkOrder::DepthFirst, None, |_| true)
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        for entry in entries {
+            match entry.kind {
+                WalkEntryKind::Dir(_) => dirs.push(entry.path),
+                WalkEntryKind::File(file) => files.push((entry.path, file)),
+            }
+        }
+        Ok((dirs, files))
+    }
+
+    // The content type is only reported in directory listings, so this looks up the
+    // listing entry of a single file.
+    pub(crate) async fn file_entry(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+    ) -> Result<Option<FileEntry>, FairOSError> {
+        let name = path.rsplit('/').next().unwrap();
+        let (_, files) = self.ls(username, pod, parent_dir(path)).await?;
+        Ok(files.into_iter().find(|entry| entry.name == name))
+    }
+
+    // Copies a file to a path of an open pod of any client, keeping its block size and
+    // compression, and returns the information of the source file.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn copy_file_to(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+        content_type: &str,
+        target: &Client,
+        target_username: &str,
+        target_pod: &str,
+        target_path: &str,
+    ) -> Result<FileInfo, FairOSError> {
+        let info = self.file_info(username, pod, path).await?;
+        let buf = self.download_buffer(username, pod, path).await?;
+        let mime = content_type
+            .parse::<Mime>()
+            .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+        let name = target_path.rsplit('/').next().unwrap();
+        target
+            .upload_buffer(
+                target_username,
+                target_pod,
+                parent_dir(target_path),
+                name,
+                buf.as_ref(),
+                mime,
+                info.block_size,
+                info.compression,
+            )
+            .await?;
+        Ok(info)
+    }
+
+    // Copies every directory and file of an open pod into an open pod of another client,
+    // keeping the block size and compression of each file.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn copy_pod<F: FnMut(&CopyProgress)>(
+        &self,
+        username: &str,
+        pod: &str,
+        target: &Client,
+        target_username: &str,
+        target_pod: &str,
+        concurrency: usize,
+        mut progress: F,
+    ) -> Result<(), FairOSError> {
+        let concurrency = concurrency.max(1);
+        let (dirs, files) = self.list_tree(username, pod, "/").await?;
+        let stores = self.list_kv_stores(username, pod).await?;
+        let databases = self.list_doc_databases(username, pod).await?;
+        let mut state = CopyProgress {
+            item: CopyItem::Dir("/".into()),
+            completed: 0,
+            total: dirs.len() + files.len() + stores.len() + databases.len(),
+        };
+        let mut report = |item: CopyItem| {
+            state.item = item;
+            state.completed += 1;
+            progress(&state);
+        };
+
+        for dir in dirs {
+            target.mkdir(target_username, target_pod, &dir).await?;
+            report(CopyItem::Dir(dir));
+        }
+
+        let mut copies = stream::iter(files)
+            .map(|(path, entry)| async move {
+                self.copy_file_to(
+                    username,
+                    pod,
+                    &path,
+                    &entry.content_type,
+                    target,
+                    target_username,
+                    target_pod,
+                    &path,
+                )
+                .await
+                .map(|_| path)
+            })
+            .buffer_unordered(concurrency);
+        while let Some(res) = copies.next().await {
+            report(CopyItem::File(res?));
+        }
+
+        for store in stores {
+            let index_type = store.index_type();
+            self.open_kv_store(username, pod, &store.name).await?;
+            target
+                .create_kv_store(target_username, target_pod, &store.name, index_type)
+                .await?;
+            target
+                .open_kv_store(target_username, target_pod, &store.name)
+                .await?;
+            let pairs = self
+                .kv_seek(username, pod, &store.name, "", None, None)
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+            stream::iter(pairs)
+                .map(|(key, value)| {
+                    let store = &store.name;
+                    async move {
+                        target
+                            .put_kv_raw(target_username, target_pod, store, &key, &value)
+                            .await
+                    }
+                })
+                .buffer_unordered(concurrency)
+                .try_collect::<Vec<_>>()
+                .await?;
+            report(CopyItem::KeyValueStore(store.name));
+        }
+
+        for database in databases {
+            self.open_doc_database(username, pod, &database.name)
+                .await?;
+            let fields = indexed_fields(&database.fields);
+            target
+                .create_doc_database(target_username, target_pod, &database.name, &fields, true)
+                .await?;
+            target
+                .open_doc_database(target_username, target_pod, &database.name)
+                .await?;
+            let count = self
+                .count_documents(username, pod, &database.name, Expr::All)
+                .await?;
+            let docs = self
+                .find_documents::<serde_json::Value>(
+                    username,
+                    pod,
+                    &database.name,
+                    Expr::All,
+                    Some(count),
+                )
+                .await?;
+            stream::iter(docs)
+                .map(|doc| {
+                    let database = &database.name;
+                    async move {
+                        let id = match doc["id"].as_str() {
+                            Some(id) => id.to_string(),
+                            None => Uuid::new_v4().to_string(),
+                        };
+                        target
+                            .put_document_with_id(target_username, target_pod, database, &id, doc)
+                            .await
+                    }
+                })
+                .buffer_unordered(concurrency)
+                .try_collect::<Vec<_>>()
+                .await?;
+            report(CopyItem::DocumentDatabase(database.name));
+        }
+        Ok(())
+    }
+
+    pub async fn migrate_user(
+        &self,
+        username: &str,
+        password: &SecretString,
+        mnemonic: &SecretString,
+        target: &mut Client,
+        target_username: &str,
+        include_pods: bool,
+    ) -> Result<String, FairOSError> {
+        let export = self.export_user(username).await?;
+        if !Self::verify_address(mnemonic.expose_secret(), &export.address)? {
+            return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+        }
+        let address = target
+            .import_with_mnemonic(target_username, password, mnemonic)
+            .await?;
+        if !address.eq_ignore_ascii_case(&export.address) {
+            log::error!(
+                "migrated address {} does not match {}",
+                address,
+                export.address
+            );
+            return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+        }
+        if include_pods {
+            let (pods, _) = self.list_pods(username).await?;
+            let (target_pods, _) = target.list_pods(target_username).await?;
+            let target_pods = target_pods.into_iter().collect::<HashSet<_>>();
+            for pod in pods.iter().filter(|pod| !target_pods.contains(*pod)) {
+                target.create_pod(target_username, pod, password).await?;
+                self.open_pod(username, pod, password).await?;
+                target.open_pod(target_username, pod, password).await?;
+                self.copy_pod(
+                    username,
+                    pod,
+                    target,
+                    target_username,
+                    pod,
+                    MIGRATION_CONCURRENCY,
+                    |_| {},
+                )
+                .await?;
+            }
+            let (target_pods, _) = target.list_pods(target_username).await?;
+            if pods.iter().any(|pod| !target_pods.contains(pod)) {
+                log::error!("pods of {} were not all migrated", username);
+                return Err(FairOSError::User(FairOSUserError::MigrationIncomplete));
+            }
+        }
+        Ok(address)
+    }
+
+    // Creates a directory along with any missing parents and returns the ones created.
+    async fn mkdir_all(
+        &self,
+        username: &str,
+        pod: &str,
+        path: &str,
+    ) -> Result<BTreeSet<String>, FairOSError> {
+        let mut created = BTreeSet::new();
+        let mut dir = String::new();
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            dir = format!("{}/{}", dir, name);
+            if created.contains(parent_dir(&dir)) || !self.dir_exists(username, pod, &dir).await? {
+                self.mkdir(username, pod, &dir).await?;
+                created.insert(dir.clone());
+            }
+        }
+        Ok(created)
+    }
+
+    pub async fn upload_dir<P: AsRef<Path>>(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        local_path: P,
+        options: &UploadOptions,
+    ) -> Result<TransferReport, FairOSError> {
+        let local_path = local_path.as_ref();
+        let matcher = UploadMatcher::new(options)?;
+        let (dirs, files) = local_tree(local_path, &matcher)?;
+        let mut created = self.mkdir_all(username, pod, dir).await?;
+        let mut report = TransferReport::default();
+        report.dirs.extend(created.iter().cloned());
+
+        // Parents come before their children, and a directory below one that was just
+        // created cannot exist yet, so only the others are checked.
+        for path in dirs {
+            let remote_path = join_path(dir, &path[1..]);
+            let res = if created.contains(parent_dir(&remote_path)) {
+                self.mkdir(username, pod, &remote_path).await
+            } else {
+                match self.dir_exists(username, pod, &remote_path).await {
+                    Ok(true) => continue,
+                    Ok(false) => self.mkdir(username, pod, &remote_path).await,
+                    Err(err) => Err(err),
+                }
+            };
+            match res {
+                Ok(_) => {
+                    created.insert(remote_path.clone());
+                    report.dirs.push(remote_path);
+                }
+                Err(err) => report.failures.push((remote_path, err)),
+            }
+        }
+
+        let mut uploads = stream::iter(files)
+            .map(|path| {
+                let (block_size, compression) = matcher.file_settings(&path);
+                let remote_path = join_path(dir, &path[1..]);
+                async move {
+                    let res = self
+                        .upload_file(
+                            username,
+                            pod,
+                            parent_dir(&remote_path),
+                            local_path.join(&path[1..]),
+                            block_size,
+                            compression,
+                        )
+                        .await;
+                    (remote_path, res)
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1));
+        while let Some((remote_path, res)) = uploads.next().await {
+            match res {
+                Ok(_) => report.files.push(remote_path),
+                Err(err) => report.failures.push((remote_path, err)),
+            }
+        }
+        report.files.sort();
+        report.failures.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+
+    pub async fn download_dir<P: AsRef<Path>>(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        local_path: P,
+        concurrency: usize,
+    ) -> Result<TransferReport, FairOSError> {
+        let local_path = local_path.as_ref();
+        let (dirs, files) = self.list_tree(username, pod, dir).await?;
+        fs::create_dir_all(local_path)
+            .map_err(|_| FairOSError::Transfer(FairOSTransferError::Io))?;
+        let mut report = TransferReport::default();
+
+        for path in dirs {
+            let local_dir = local_path.join(&relative_path(dir, &path)[1..]);
+            match fs::create_dir_all(local_dir) {
+                Ok(_) => report.dirs.push(path),
+                Err(_) => report
+                    .failures
+                    .push((path, FairOSError::Transfer(FairOSTransferError::Io))),
+            }
+        }
+
+        let mut pending = Vec::new();
+        for (path, entry) in files {
+            let local_file = local_path.join(&relative_path(dir, &path)[1..]);
+            if is_unchanged(&local_file, &entry) {
+                report.skipped.push(path);
+            } else {
+                pending.push((path, entry, local_file));
+            }
+        }
+
+        let mut downloads = stream::iter(pending)
+            .map(|(path, entry, local_file)| async move {
+                let res = self
+                    .download_file(username, pod, &path, &local_file)
+                    .await
+                    .and_then(|_| {
+                        let mtime = FileTime::from_unix_time(entry.modification_time as i64, 0);
+                        filetime::set_file_mtime(&local_file, mtime)
+                            .map_err(|_| FairOSError::Transfer(FairOSTransferError::Io))
+                    });
+                (path, res)
+            })
+            .buffer_unordered(concurrency.max(1));
+        while let Some((path, res)) = downloads.next().await {
+            match res {
+                Ok(_) => report.files.push(path),
+                Err(err) => report.failures.push((path, err)),
+            }
+        }
+        report.files.sort();
+        report.skipped.sort();
+        report.failures.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+
+    pub async fn copy_file(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        dst_pod: &str,
+        dst_path: &str,
+    ) -> Result<(), FairOSError> {
+        validate_destination(src_pod, src_path, dst_pod, dst_path)?;
+        let entry = self
+            .file_entry(username, src_pod, src_path)
+            .await?
+            .ok_or(FairOSError::FileSystem(FairOSFileSystemError::Error))?;
+        self.copy_file_to(
+            username,
+            src_pod,
+            src_path,
+            &entry.content_type,
+            self,
+            username,
+            dst_pod,
+            dst_path,
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn copy_dir(
+        &self,
+        username: &str,
+        src_pod: &str,
+        src_path: &str,
+        dst_pod: &str,
+        dst_path: &str,
+        concurrency: usize,
+    ) -> Result<TransferReport, FairOSError> {
+        validate_destination(src_pod, src_path, dst_pod, dst_path)?;
+        let (dirs, files) = self.list_tree(username, src_pod, src_path).await?;
+        let target = |path: &str| {
+            format!(
+                "{}{}",
+                dst_path.trim_end_matches('/'),
+                relative_path(src_path, path)
+            )
+        };
+        let mut report = TransferReport::default();
+
+        let dst_dirs = Some(dst_path.trim_end_matches('/').to_string())
+            .into_iter()
+            .chain(dirs.iter().map(|dir| target(dir)));
+        for dir in dst_dirs {
+            let res = match self.dir_exists(username, dst_pod, &dir).await {
+                Ok(true) => continue,
+                Ok(false) => self.mkdir(username, dst_pod, &dir).await,
+                Err(err) => Err(err),
+            };
+            match res {
+                Ok(_) => report.dirs.push(dir),
+                Err(err) => report.failures.push((dir, err)),
+            }
+        }
+
+        let mut copies = stream::iter(files)
+            .map(|(path, entry)| {
+                let dst_file = target(&path);
+                async move {
+                    let res = self
+                        .copy_file_to(
+                            username,
+                            src_pod,
+                            &path,
+                            &entry.content_type,
+                            self,
+                            username,
+                            dst_pod,
+                            &dst_file,
+                        )
+                        .await;
+                    (dst_file, res)
+                }
+            })
+            .buffer_unordered(concurrency.max(1));
+        while let Some((path, res)) = copies.next().await {
+            match res {
+                Ok(_) => report.files.push(path),
+                Err(err) => report.failures.push((path, err)),
+            }
+        }
+        report.files.sort();
+        report.failures.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_unchanged, join_path, local_tree, parent_dir, Client, CopyItem, UploadMatcher,
+        UploadOptions, UploadRule,
+    };
+    use crate::{BlockSize, Compression, FileEntry, IndexType};
+    use filetime::FileTime;
+    use futures::TryStreamExt;
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+    use std::{fs, path::Path};
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    #[tokio::test]
+    async fn test_join_path() {
+        assert_eq!(join_path("/", "cat.jpg"), "/cat.jpg");
+        assert_eq!(join_path("/photos", "cat.jpg"), "/photos/cat.jpg");
+        assert_eq!(join_path("/photos/", "cat.jpg"), "/photos/cat.jpg");
+    }
+
+    #[tokio::test]
+    async fn test_parent_dir() {
+        assert_eq!(parent_dir("/cat.jpg"), "/");
+        assert_eq!(parent_dir("/photos/cat.jpg"), "/photos");
+        assert_eq!(parent_dir("/photos/cats/cat.jpg"), "/photos/cats");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_user_succeeds() {
+        let mut source = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = source.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let (address, mnemonic) = res.unwrap();
+        let mnemonic = mnemonic.unwrap();
+        let pod = random_name();
+        let res = source.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = source.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = source
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                Some(Compression::Gzip),
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = source
+            .create_kv_store(&username, &pod, "table1", IndexType::Str)
+            .await;
+        assert!(res.is_ok());
+        let res = source
+            .put_kv_pair(&username, &pod, "table1", "key1", "value1")
+            .await;
+        assert!(res.is_ok());
+        // A second user stands in for the account on the target server.
+        let mut target = Client::new();
+        let target_username = random_name();
+        let res = source
+            .migrate_user(
+                &username,
+                &password,
+                &mnemonic,
+                &mut target,
+                &target_username,
+                true,
+            )
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), address);
+        let res = target.list_pods(&target_username).await;
+        assert!(res.unwrap().0.contains(&pod));
+        let res = target
+            .download_buffer(&target_username, &pod, "/photos/cat.txt")
+            .await;
+        assert_eq!(res.unwrap().as_ref(), b"meow");
+        let res = target
+            .get_kv_pair::<String>(&target_username, &pod, "table1", "key1")
+            .await;
+        assert_eq!(res.unwrap(), "value1");
+    }
+
+    #[tokio::test]
+    async fn test_copy_pod_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod1 = random_name();
+        let pod2 = random_name();
+        for pod in [&pod1, &pod2] {
+            let res = fairos.create_pod(&username, pod, &password).await;
+            assert!(res.is_ok());
+        }
+        let res = fairos.mkdir(&username, &pod1, "/photos").await;
+        assert!(res.is_ok());
+        for name in ["cat1.txt", "cat2.txt"] {
+            let res = fairos
+                .upload_buffer(
+                    &username,
+                    &pod1,
+                    "/photos",
+                    name,
+                    "meow".as_bytes(),
+                    mime::TEXT_PLAIN,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await;
+            assert!(res.is_ok());
+        }
+        let res = fairos
+            .create_kv_store(&username, &pod1, "table1", IndexType::Str)
+            .await;
+        assert!(res.is_ok());
+        for (key, value) in [("key1", "value1"), ("key2", "value2"), ("key3", "value3")] {
+            let res = fairos
+                .put_kv_pair(&username, &pod1, "table1", key, value)
+                .await;
+            assert!(res.is_ok());
+        }
+        let mut items = Vec::new();
+        let res = fairos
+            .copy_pod(&username, &pod1, &fairos, &username, &pod2, 2, |progress| {
+                assert!(progress.completed <= progress.total);
+                items.push(progress.item.clone());
+            })
+            .await;
+        assert!(res.is_ok());
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0], CopyItem::Dir("/photos".into()));
+        let res = fairos
+            .download_buffer(&username, &pod2, "/photos/cat2.txt")
+            .await;
+        assert_eq!(res.unwrap().as_ref(), b"meow");
+        let mut tables = Vec::new();
+        for pod in [&pod1, &pod2] {
+            let res = fairos.open_kv_store(&username, pod, "table1").await;
+            assert!(res.is_ok());
+            let res = fairos
+                .kv_seek(&username, pod, "table1", "", None, None)
+                .await;
+            let res = res.unwrap().try_collect::<Vec<_>>().await;
+            tables.push(res.unwrap());
+        }
+        assert_eq!(tables[0].len(), 3);
+        assert_eq!(tables[0], tables[1]);
+    }
+
+    fn write_test_dir(root: &str) {
+        fs::create_dir_all(format!("{}/photos/cats", root)).unwrap();
+        fs::create_dir_all(format!("{}/tmp", root)).unwrap();
+        fs::write(format!("{}/notes.txt", root), "meow").unwrap();
+        fs::write(format!("{}/photos/cat.jpg", root), "meow").unwrap();
+        fs::write(format!("{}/photos/cats/kitten.jpg", root), "mew").unwrap();
+        fs::write(format!("{}/tmp/cat.jpg", root), "meow").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_tree() {
+        let root = random_name();
+        write_test_dir(&root);
+        let options = UploadOptions {
+            rules: vec![UploadRule {
+                pattern: "photos/**".into(),
+                block_size: BlockSize::Kilobytes(1),
+                compression: Some(Compression::Gzip),
+            }],
+            include: vec!["*.jpg".into()],
+            exclude: vec!["tmp".into()],
+            ..Default::default()
+        };
+        let matcher = UploadMatcher::new(&options).unwrap();
+        let res = local_tree(root.as_ref(), &matcher);
+        fs::remove_dir_all(&root).unwrap();
+        let (mut dirs, mut files) = res.unwrap();
+        dirs.sort();
+        files.sort();
+        assert_eq!(dirs, vec!["/photos", "/photos/cats"]);
+        assert_eq!(files, vec!["/photos/cat.jpg", "/photos/cats/kitten.jpg"]);
+        assert_eq!(
+            matcher.file_settings("/photos/cat.jpg"),
+            (BlockSize::Kilobytes(1), Some(Compression::Gzip))
+        );
+        assert_eq!(
+            matcher.file_settings("/notes.txt"),
+            (BlockSize::Megabytes(1), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_dir_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let root = random_name();
+        write_test_dir(&root);
+        let options = UploadOptions {
+            exclude: vec!["tmp".into()],
+            ..Default::default()
+        };
+        let res = fairos
+            .upload_dir(&username, &pod, "/backups/cats", &root, &options)
+            .await;
+        let res2 = fairos
+            .upload_dir(&username, &pod, "/backups/cats", &root, &options)
+            .await;
+        fs::remove_dir_all(&root).unwrap();
+        assert!(res.is_ok());
+        let report = res.unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(
+            report.dirs,
+            vec![
+                "/backups",
+                "/backups/cats",
+                "/backups/cats/photos",
+                "/backups/cats/photos/cats"
+            ]
+        );
+        assert_eq!(
+            report.files,
+            vec![
+                "/backups/cats/notes.txt",
+                "/backups/cats/photos/cat.jpg",
+                "/backups/cats/photos/cats/kitten.jpg"
+            ]
+        );
+        let res = fairos
+            .ls(&username, &pod, "/backups/cats/photos/cats")
+            .await;
+        assert!(res.is_ok());
+        let (_, files) = res.unwrap();
+        assert_eq!(files[0].name, "kitten.jpg");
+        assert!(res2.is_ok());
+        assert!(res2.unwrap().dirs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_is_unchanged() {
+        let path = random_name();
+        fs::write(&path, "meow").unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(1_650_000_000, 0)).unwrap();
+        let mut entry = FileEntry {
+            name: path.clone(),
+            content_type: "text/plain".into(),
+            size: 4,
+            block_size: BlockSize::Kilobytes(1),
+            creation_time: 1_650_000_000,
+            modification_time: 1_650_000_000,
+            access_time: 1_650_000_000,
+        };
+        let unchanged = is_unchanged(Path::new(&path), &entry);
+        entry.modification_time += 1;
+        let modified = is_unchanged(Path::new(&path), &entry);
+        entry.modification_time -= 1;
+        entry.size = 5;
+        let resized = is_unchanged(Path::new(&path), &entry);
+        fs::remove_file(&path).unwrap();
+        assert!(unchanged);
+        assert!(!modified);
+        assert!(!resized);
+        assert!(!is_unchanged(Path::new(&path), &entry));
+    }
+
+    #[tokio::test]
+    async fn test_download_dir_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                None,
+            )
+            .await;
+        assert!(res.is_ok());
+        let root = random_name();
+        let res = fairos.download_dir(&username, &pod, "/", &root, 4).await;
+        let content = fs::read_to_string(format!("{}/photos/cat.txt", root));
+        let res2 = fairos.download_dir(&username, &pod, "/", &root, 4).await;
+        fs::remove_dir_all(&root).unwrap();
+        assert!(res.is_ok());
+        let report = res.unwrap();
+        assert_eq!(report.dirs, vec!["/photos"]);
+        assert_eq!(report.files, vec!["/photos/cat.txt"]);
+        assert_eq!(content.unwrap(), "meow");
+        assert!(res2.is_ok());
+        let report = res2.unwrap();
+        assert!(report.files.is_empty());
+        assert_eq!(report.skipped, vec!["/photos/cat.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_copy_dir_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let pod2 = random_name();
+        let res = fairos.create_pod(&username, &pod2, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.jpg",
+                "meow".as_bytes(),
+                mime::IMAGE_JPEG,
+                BlockSize::Kilobytes(2),
+                Some(Compression::Gzip),
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .copy_file(&username, &pod, "/photos/cat.jpg", &pod, "/cat.jpg")
+            .await;
+        assert!(res.is_ok());
+        let res = fairos
+            .copy_dir(&username, &pod, "/photos", &pod, "/photos/cats", 4)
+            .await;
+        assert!(res.is_err());
+        let res = fairos
+            .copy_dir(&username, &pod, "/photos", &pod2, "/cats", 4)
+            .await;
+        assert!(res.is_ok());
+        let report = res.unwrap();
+        assert_eq!(report.dirs, vec!["/cats"]);
+        assert_eq!(report.files, vec!["/cats/cat.jpg"]);
+        let res = fairos.file_info(&username, &pod2, "/cats/cat.jpg").await;
+        let info = res.unwrap();
+        assert_eq!(info.block_size, BlockSize::Kilobytes(2));
+        assert_eq!(info.compression, Some(Compression::Gzip));
+        let res = fairos.ls(&username, &pod2, "/cats").await;
+        let (_, files) = res.unwrap();
+        assert_eq!(files[0].content_type, "image/jpeg");
+        let res = fairos.download_buffer(&username, &pod, "/cat.jpg").await;
+        assert_eq!(res.unwrap(), "meow".as_bytes());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{error::FairOSError, transfer::parent_dir, Client, FileInfo};
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DirUsage {
+    pub path: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub blocks: u32,
+    pub files: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PodUsage {
+    pub pod: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub blocks: u32,
+    pub files: u32,
+    pub content_types: BTreeMap<String, u32>,
+    pub dirs: Vec<DirUsage>,
+}
+
+// Returns the directories that contain a path, from the root down.
+fn ancestors(path: &str) -> Vec<&str> {
+    let mut dirs = Vec::new();
+    let mut dir = path;
+    while dir != "/" {
+        dir = parent_dir(dir);
+        dirs.push(dir);
+    }
+    dirs.reverse();
+    dirs
+}
+
+// The content type comes from the directory listing, since file info does not report it.
+fn add_file(
+    usage: &mut PodUsage,
+    dirs: &mut BTreeMap<String, DirUsage>,
+    info: &FileInfo,
+    content_type: &str,
+) {
+    let path = if info.path.ends_with('/') {
+        format!("{}{}", info.path, info.name)
+    } else {
+        format!("{}/{}", info.path, info.name)
+    };
+    let size = info.size as u64;
+    // Without a block list the compressed size is not known, so the file counts as
+    // uncompressed.
+    let compressed_size = if info.blocks.is_empty() {
+        size
+    } else {
+        info.blocks
+            .iter()
+            .map(|block| block.compressed_size as u64)
+            .sum::<u64>()
+    };
+    let blocks = info.blocks.len() as u32;
+
+    usage.size += size;
+    usage.compressed_size += compressed_size;
+    usage.blocks += blocks;
+    usage.files += 1;
+    *usage.content_types.entry(content_type.into()).or_default() += 1;
+    for dir in ancestors(&path) {
+        let dir_usage = dirs.entry(dir.into()).or_insert_with(|| DirUsage {
+            path: dir.into(),
+            ..Default::default()
+        });
+        dir_usage.size += size;
+        dir_usage.compressed_size += compressed_size;
+        dir_usage.blocks += blocks;
+        dir_usage.files += 1;
+    }
+}
+
+impl Client {
+    pub async fn pod_usage(&self, username: &str, pod: &str) -> Result<PodUsage, FairOSError> {
+        let (tree_dirs, files) = self.list_tree(username, pod, "/").await?;
+        let mut usage = PodUsage {
+            pod: pod.into(),
+            ..Default::default()
+        };
+        let mut dirs = BTreeMap::new();
+        for dir in ["/".to_string()].into_iter().chain(tree_dirs) {
+            dirs.insert(
+                dir.clone(),
+                DirUsage {
+                    path: dir,
+                    ..Default::default()
+                },
+            );
+        }
+        for (path, entry) in files {
+            let info = self.file_info(username, pod, &path).await?;
+            add_file(&mut usage, &mut dirs, &info, &entry.content_type);
+        }
+        usage.dirs = dirs.into_values().collect();
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_file, ancestors, Client, PodUsage};
+    use crate::{BlockSize, FileBlock, FileInfo};
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+    use std::collections::BTreeMap;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn test_file_info(path: &str, name: &str, sizes: &[(u32, u32)]) -> FileInfo {
+        FileInfo {
+            pod: "pod".into(),
+            path: path.into(),
+            name: name.into(),
+            content_type: None,
+            size: sizes.iter().map(|(size, _)| size).sum(),
+            block_size: BlockSize::Kilobytes(1),
+            compression: None,
+            creation_time: 0,
+            modification_time: 0,
+            access_time: 0,
+            blocks: sizes
+                .iter()
+                .enumerate()
+                .map(|(i, (size, compressed_size))| FileBlock {
+                    name: format!("block-{}", i),
+                    reference: "".into(),
+                    size: *size,
+                    compressed_size: *compressed_size,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ancestors() {
+        assert_eq!(ancestors("/cat.jpg"), vec!["/"]);
+        assert_eq!(
+            ancestors("/photos/cats/cat.jpg"),
+            vec!["/", "/photos", "/photos/cats"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_file() {
+        let mut usage = PodUsage::default();
+        let mut dirs = BTreeMap::new();
+        let info = test_file_info("/photos", "cat.jpg", &[(1000, 800), (500, 400)]);
+        add_file(&mut usage, &mut dirs, &info, "image/jpeg");
+        let info = test_file_info("/", "notes.txt", &[(100, 50)]);
+        add_file(&mut usage, &mut dirs, &info, "text/plain");
+        let mut info = test_file_info("/", "todo.txt", &[]);
+        info.size = 30;
+        add_file(&mut usage, &mut dirs, &info, "text/plain");
+        assert_eq!(usage.size, 1630);
+        assert_eq!(usage.compressed_size, 1280);
+        assert_eq!(usage.blocks, 3);
+        assert_eq!(usage.files, 3);
+        assert_eq!(usage.content_types.get("image/jpeg"), Some(&1));
+        assert_eq!(usage.content_types.get("text/plain"), Some(&2));
+        assert_eq!(dirs["/"].size, 1630);
+        assert_eq!(dirs["/"].files, 3);
+        assert_eq!(dirs["/photos"].compressed_size, 1200);
+        assert_eq!(dirs["/photos"].files, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pod_usage_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let res = fairos.mkdir(&username, &pod, "/photos").await;
+        assert!(res.is_ok());
+        let res = fairos
+            .upload_buffer(
+                &username,
+                &pod,
+                "/photos",
+                "cat.txt",
+                "meow".as_bytes(),
+                mime::TEXT_PLAIN,
+                BlockSize::Kilobytes(1),
+                None,
+            )
+            .await;
+        assert!(res.is_ok());
+        let res = fairos.pod_usage(&username, &pod).await;
+        assert!(res.is_ok());
+        let usage = res.unwrap();
+        assert_eq!(usage.size, 4);
+        assert_eq!(usage.compressed_size, 4);
+        assert_eq!(usage.files, 1);
+        assert_eq!(usage.content_types.get("text/plain"), Some(&1));
+        assert_eq!(usage.dirs.len(), 2);
+        assert_eq!(usage.dirs[1].path, "/photos");
+        assert_eq!(usage.dirs[1].size, 4);
+    }
+}
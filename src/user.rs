@@ -1,20 +1,54 @@
 use crate::{
-    client::{MessageResponse, RequestError},
-    Client, FairOSError, FairOSUserError,
+    client::{secret_body, MessageResponse, RequestError},
+    AccountRecovery, Client, FairOSError, FairOSUserError,
 };
 
 use std::collections::HashMap;
 
-use bip39::Mnemonic;
+use bip32::{DerivationPath, XPrv};
+use bip39::{Language, Mnemonic};
 use rand::Rng;
 use rand_chacha::ChaCha20Rng;
-use serde::Deserialize;
-use serde_json::json;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use zeroize::Zeroizing;
+
+// BIP-44 path of the first Ethereum account, as used by fairOS-dfs.
+const DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+#[derive(Serialize)]
+struct UserSignupRequest<'a> {
+    user_name: &'a str,
+    password: &'a str,
+    mnemonic: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct UserLoginRequest<'a> {
+    user_name: &'a str,
+    password: &'a str,
+}
+
+#[derive(Serialize)]
+struct UserImportRequest<'a> {
+    user_name: &'a str,
+    password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mnemonic: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct UserDeleteRequest<'a> {
+    password: &'a str,
+}
 
 #[derive(Debug, Deserialize)]
 struct UserSignupResponse {
     address: String,
-    mnemonic: Option<String>,
+    mnemonic: Option<SecretString>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,6 +78,45 @@ struct UserStatResponse {
     address: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordCount {
+    Twelve,
+    Fifteen,
+    Eighteen,
+    TwentyOne,
+    TwentyFour,
+}
+
+impl WordCount {
+    fn entropy_len(&self) -> usize {
+        match self {
+            WordCount::Twelve => 16,
+            WordCount::Fifteen => 20,
+            WordCount::Eighteen => 24,
+            WordCount::TwentyOne => 28,
+            WordCount::TwentyFour => 32,
+        }
+    }
+}
+
+fn to_checksum_address(address: &[u8]) -> String {
+    let address = hex::encode(address);
+    let hash = Keccak256::digest(address.as_bytes());
+    let checksummed = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    format!("0x{}", checksummed)
+}
+
 #[derive(Debug)]
 pub struct UserExport {
     pub username: String,
@@ -57,28 +130,63 @@ pub struct UserInfo {
 }
 
 impl Client {
-    pub fn generate_mnemonic(rng: &mut ChaCha20Rng) -> String {
-        let mut entropy = [0u8; 16];
-        rng.fill(&mut entropy);
-        Mnemonic::from_entropy(&entropy).unwrap().to_string()
+    pub fn generate_mnemonic(rng: &mut ChaCha20Rng) -> SecretString {
+        Self::generate_mnemonic_with(rng, WordCount::Twelve, Language::English)
+    }
+
+    pub fn generate_mnemonic_with(
+        rng: &mut ChaCha20Rng,
+        word_count: WordCount,
+        language: Language,
+    ) -> SecretString {
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        let entropy = &mut entropy[..word_count.entropy_len()];
+        rng.fill(&mut *entropy);
+        let mnemonic = Mnemonic::from_entropy_in(language, entropy).unwrap();
+        SecretString::from(mnemonic.to_string())
+    }
+
+    pub fn validate_mnemonic(mnemonic: &str) -> bool {
+        Mnemonic::parse(mnemonic).is_ok()
+    }
+
+    pub fn derive_address(mnemonic: &str) -> Result<String, FairOSError> {
+        let mnemonic = Mnemonic::parse(mnemonic)
+            .map_err(|_| FairOSError::User(FairOSUserError::InvalidMnemonic))?;
+        let seed = mnemonic.to_seed("");
+        let path = DERIVATION_PATH.parse::<DerivationPath>().unwrap();
+        let key = XPrv::derive_from_path(seed, &path).unwrap();
+        let point = key.public_key().public_key().to_encoded_point(false);
+        let hash = Keccak256::digest(&point.as_bytes()[1..]);
+        Ok(to_checksum_address(&hash[12..]))
+    }
+
+    pub fn verify_address(mnemonic: &str, address: &str) -> Result<bool, FairOSError> {
+        let derived = Self::derive_address(mnemonic)?;
+        Ok(derived.eq_ignore_ascii_case(address))
     }
 
     pub async fn signup(
         &mut self,
         username: &str,
-        password: &str,
-        mnemonic: Option<&str>,
-    ) -> Result<(String, Option<String>), FairOSError> {
-        let data = json!({
-            "user_name": username,
-            "password": password,
-            "mnemonic": mnemonic,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        password: &SecretString,
+        mnemonic: Option<&SecretString>,
+    ) -> Result<(String, Option<SecretString>), FairOSError> {
+        self.username_policy().validate(username)?;
+        self.password_policy().validate(password.expose_secret())?;
+        let mnemonic = mnemonic.map(|mnemonic| mnemonic.expose_secret());
+        // A given mnemonic is checked before anything is created on the server.
+        let expected_address = match mnemonic {
+            Some(mnemonic) => Some(Self::derive_address(mnemonic)?),
+            None => None,
+        };
+        let data = secret_body(&UserSignupRequest {
+            user_name: username,
+            password: password.expose_secret(),
+            mnemonic,
+        });
         let (res, cookie) = self
-            .post::<UserSignupResponse>("/user/signup", data, None)
+            .post_secret::<UserSignupResponse>("/user/signup", data, None)
             .await
             .map_err(|err| match err {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
@@ -90,17 +198,32 @@ impl Client {
                 },
             })?;
         self.set_cookie(username, cookie.unwrap());
+        let verified = match (&expected_address, &res.mnemonic) {
+            (Some(address), _) => Ok(address.eq_ignore_ascii_case(&res.address)),
+            (None, Some(mnemonic)) => Self::verify_address(mnemonic.expose_secret(), &res.address),
+            (None, None) => Ok(true),
+        };
+        if !matches!(verified, Ok(true)) {
+            log::error!("server address {} does not match mnemonic", res.address);
+            // Remove the account again so the caller is not left logged in to it.
+            if let Err(err) = self.delete_user(username, password).await {
+                log::warn!("could not delete user {}: {:?}", username, err);
+                self.remove_cookie(username);
+            }
+            return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+        }
         Ok((res.address, res.mnemonic))
     }
 
-    pub async fn login(&mut self, username: &str, password: &str) -> Result<(), FairOSError> {
-        let data = json!({
-            "user_name": username,
-            "password": password,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+    pub async fn login(
+        &mut self,
+        username: &str,
+        password: &SecretString,
+    ) -> Result<(), FairOSError> {
+        let data = secret_body(&UserLoginRequest {
+            user_name: username,
+            password: password.expose_secret(),
+        });
         let (_, cookie) = self
             .post::<MessageResponse>("/user/login", data, None)
             .await
@@ -116,6 +239,8 @@ impl Client {
                     _ => FairOSError::User(FairOSUserError::Error),
                 },
             })?;
+        // Pods tracked for an earlier session are not open in the new one.
+        self.take_open_pods(username);
         self.set_cookie(username, cookie.unwrap());
         Ok(())
     }
@@ -123,17 +248,17 @@ impl Client {
     pub async fn import_with_address(
         &mut self,
         username: &str,
-        password: &str,
+        password: &SecretString,
         address: &str,
     ) -> Result<String, FairOSError> {
-        let data = json!({
-            "user_name": username,
-            "password": password,
-            "address": address,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        self.username_policy().validate(username)?;
+        self.password_policy().validate(password.expose_secret())?;
+        let data = secret_body(&UserImportRequest {
+            user_name: username,
+            password: password.expose_secret(),
+            address: Some(address),
+            mnemonic: None,
+        });
         let (res, cookie) = self
             .post::<UserImportResponse>("/user/import", data, None)
             .await
@@ -148,17 +273,32 @@ impl Client {
     pub async fn import_with_mnemonic(
         &mut self,
         username: &str,
-        password: &str,
-        mnemonic: &str,
+        password: &SecretString,
+        mnemonic: &SecretString,
     ) -> Result<String, FairOSError> {
-        let data = json!({
-            "user_name": username,
-            "password": password,
-            "mnemonic": mnemonic,
-        })
-        .to_string()
-        .as_bytes()
-        .to_vec();
+        self.username_policy().validate(username)?;
+        self.password_policy().validate(password.expose_secret())?;
+        self.import_mnemonic(username, password, mnemonic).await
+    }
+
+    // Imports without applying the local policies, so that an account can always be
+    // restored with the password it had before.
+    async fn import_mnemonic(
+        &mut self,
+        username: &str,
+        password: &SecretString,
+        mnemonic: &SecretString,
+    ) -> Result<String, FairOSError> {
+        let mnemonic = mnemonic.expose_secret();
+        if !Self::validate_mnemonic(mnemonic) {
+            return Err(FairOSError::User(FairOSUserError::InvalidMnemonic));
+        }
+        let data = secret_body(&UserImportRequest {
+            user_name: username,
+            password: password.expose_secret(),
+            address: None,
+            mnemonic: Some(mnemonic),
+        });
         let (res, cookie) = self
             .post::<UserImportResponse>("/user/import", data, None)
             .await
@@ -167,14 +307,26 @@ impl Client {
                 RequestError::Message(_) => FairOSError::User(FairOSUserError::Error),
             })?;
         self.set_cookie(username, cookie.unwrap());
+        if !matches!(Self::verify_address(mnemonic, &res.address), Ok(true)) {
+            log::error!("server address {} does not match mnemonic", res.address);
+            // Remove the imported account again so the caller is not left logged in to it.
+            if let Err(err) = self.delete_user(username, password).await {
+                log::warn!("could not delete user {}: {:?}", username, err);
+                self.remove_cookie(username);
+            }
+            return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+        }
         Ok(res.address)
     }
 
-    pub async fn delete_user(&mut self, username: &str, password: &str) -> Result<(), FairOSError> {
-        let data = json!({ "password": password })
-            .to_string()
-            .as_bytes()
-            .to_vec();
+    pub async fn delete_user(
+        &mut self,
+        username: &str,
+        password: &SecretString,
+    ) -> Result<(), FairOSError> {
+        let data = secret_body(&UserDeleteRequest {
+            password: password.expose_secret(),
+        });
         let cookie = self.cookie(username).unwrap();
         let _: MessageResponse =
             self.delete("/user/delete", data, cookie)
@@ -183,10 +335,52 @@ impl Client {
                     RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                     RequestError::Message(_) => FairOSError::User(FairOSUserError::Error),
                 })?;
+        self.take_open_pods(username);
         self.remove_cookie(username);
         Ok(())
     }
 
+    pub async fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &SecretString,
+        new_password: &SecretString,
+        mnemonic: &SecretString,
+    ) -> Result<(), FairOSError> {
+        self.password_policy()
+            .validate(new_password.expose_secret())?;
+        let export = self.export_user(username).await?;
+        if !Self::verify_address(mnemonic.expose_secret(), &export.address)? {
+            return Err(FairOSError::User(FairOSUserError::AddressMismatch));
+        }
+        // The server cannot import a user that still exists, so the account has to be
+        // deleted first. If it cannot be imported again, the mnemonic is handed back.
+        self.delete_user(username, old_password).await?;
+        match self.import_mnemonic(username, new_password, mnemonic).await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!(
+                    "could not import {} with new password, rolling back",
+                    username
+                );
+                if self
+                    .import_mnemonic(username, old_password, mnemonic)
+                    .await
+                    .is_err()
+                {
+                    log::error!("could not restore {} with old password", username);
+                    return Err(FairOSError::User(FairOSUserError::PasswordChangeFailed(
+                        AccountRecovery {
+                            username: username.into(),
+                            mnemonic: mnemonic.clone(),
+                        },
+                    )));
+                }
+                Err(err)
+            }
+        }
+    }
+
     pub async fn user_exists(&self, username: &str) -> Result<bool, FairOSError> {
         let mut query = HashMap::new();
         query.insert("user_name", username);
@@ -214,6 +408,11 @@ impl Client {
     }
 
     pub async fn logout(&mut self, username: &str) -> Result<(), FairOSError> {
+        for pod in self.open_pods(username) {
+            if self.close_pod(username, &pod).await.is_err() {
+                log::warn!("could not close pod {} of {}", pod, username);
+            }
+        }
         let cookie = self.cookie(username).unwrap();
         let _ = self
             .post::<MessageResponse>("/user/logout", Vec::new(), Some(cookie))
@@ -222,6 +421,8 @@ impl Client {
                 RequestError::CouldNotConnect => FairOSError::CouldNotConnect,
                 RequestError::Message(_) => FairOSError::User(FairOSUserError::Error),
             })?;
+        // Pods stay tracked if logging out fails, since they are still open on the server.
+        self.take_open_pods(username);
         self.remove_cookie(username);
         Ok(())
     }
@@ -259,12 +460,14 @@ impl Client {
 
 #[cfg(test)]
 mod tests {
-    use super::{Client, FairOSError, FairOSUserError};
+    use super::{AccountRecovery, Client, FairOSError, FairOSUserError, Language, WordCount};
+    use crate::PasswordPolicy;
     use rand::{
         distributions::{Alphanumeric, Uniform},
         thread_rng, Rng, SeedableRng,
     };
     use rand_chacha::ChaCha20Rng;
+    use secrecy::{ExposeSecret, SecretString};
 
     fn random_name() -> String {
         thread_rng()
@@ -274,19 +477,75 @@ mod tests {
             .collect()
     }
 
-    fn random_password() -> String {
-        thread_rng()
+    fn random_password() -> SecretString {
+        let password = thread_rng()
             .sample_iter(Uniform::new_inclusive(0, 255))
             .take(8)
             .map(char::from)
-            .collect()
+            .collect::<String>();
+        SecretString::from(password)
     }
 
     #[tokio::test]
     async fn test_generate_mnemonic() {
         let mut rng = ChaCha20Rng::from_entropy();
         let mnemonic = Client::generate_mnemonic(&mut rng);
-        assert_eq!(mnemonic.split(" ").count(), 12);
+        assert_eq!(mnemonic.expose_secret().split(" ").count(), 12);
+    }
+
+    #[tokio::test]
+    async fn test_generate_mnemonic_with_succeeds() {
+        let mut rng = ChaCha20Rng::from_entropy();
+        for (word_count, n) in [
+            (WordCount::Twelve, 12),
+            (WordCount::Fifteen, 15),
+            (WordCount::Eighteen, 18),
+            (WordCount::TwentyOne, 21),
+            (WordCount::TwentyFour, 24),
+        ] {
+            let mnemonic = Client::generate_mnemonic_with(&mut rng, word_count, Language::English);
+            assert_eq!(mnemonic.expose_secret().split(" ").count(), n);
+            assert!(Client::validate_mnemonic(mnemonic.expose_secret()));
+        }
+        let mnemonic =
+            Client::generate_mnemonic_with(&mut rng, WordCount::TwentyFour, Language::Spanish);
+        assert_eq!(mnemonic.expose_secret().split(" ").count(), 24);
+        assert!(Client::validate_mnemonic(mnemonic.expose_secret()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_mnemonic_succeeds() {
+        let mnemonic = "brick salad slogan group happy exact wash way keen park amount concert";
+        assert!(Client::validate_mnemonic(mnemonic));
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        assert!(!Client::validate_mnemonic(mnemonic));
+        let mnemonic = "not a mnemonic";
+        assert!(!Client::validate_mnemonic(mnemonic));
+    }
+
+    #[tokio::test]
+    async fn test_derive_address_succeeds() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let res = Client::derive_address(mnemonic);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+        let res = Client::derive_address("abandon abandon");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::User(FairOSUserError::InvalidMnemonic),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_address_succeeds() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let res = Client::verify_address(mnemonic, "0x9858effd232b4033e47d90003d41ec34ecaeda94");
+        assert!(res.is_ok());
+        assert!(res.unwrap());
+        let res = Client::verify_address(mnemonic, "0x0000000000000000000000000000000000000000");
+        assert!(res.is_ok());
+        assert!(!res.unwrap());
     }
 
     #[tokio::test]
@@ -331,6 +590,87 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_signup_invalid_mnemonic_fails() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let mnemonic = SecretString::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon");
+        let res = fairos.signup(&username, &password, Some(&mnemonic)).await;
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::User(FairOSUserError::InvalidMnemonic),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signup_password_policy_fails() {
+        let mut fairos = Client::new();
+        fairos.set_password_policy(PasswordPolicy::default());
+        let username = random_name();
+        let password = SecretString::from("password");
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::User(FairOSUserError::PasswordMissingUppercase),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_password_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let (address, mnemonic) = res.unwrap();
+        let new_password = random_password();
+        let res = fairos
+            .change_password(&username, &password, &new_password, &mnemonic.unwrap())
+            .await;
+        assert!(res.is_ok());
+        let res = fairos.logout(&username).await;
+        assert!(res.is_ok());
+        let res = fairos.login(&username, &password).await;
+        assert!(res.is_err());
+        let res = fairos.login(&username, &new_password).await;
+        assert!(res.is_ok());
+        let res = fairos.export_user(&username).await;
+        assert_eq!(res.unwrap().address, address);
+    }
+
+    #[tokio::test]
+    async fn test_account_recovery_debug_is_redacted() {
+        let recovery = AccountRecovery {
+            username: "username".into(),
+            mnemonic: SecretString::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+        };
+        let debug = format!("{:?}", recovery);
+        assert!(debug.contains("username"));
+        assert!(!debug.contains("abandon"));
+    }
+
+    #[tokio::test]
+    async fn test_change_password_policy_fails() {
+        let mut fairos = Client::new();
+        fairos.set_password_policy(PasswordPolicy::default());
+        let mnemonic = SecretString::from("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+        let res = fairos
+            .change_password(
+                &random_name(),
+                &random_password(),
+                &SecretString::from("short"),
+                &mnemonic,
+            )
+            .await;
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::User(FairOSUserError::PasswordTooShort),
+        );
+    }
+
     #[tokio::test]
     async fn test_login_succeeds() {
         let mut fairos = Client::new();
@@ -412,6 +752,22 @@ mod tests {
         assert_eq!(address1, address2);
     }
 
+    #[tokio::test]
+    async fn test_import_with_mnemonic_invalid_mnemonic_fails() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let mnemonic = SecretString::from("brick salad slogan");
+        let res = fairos
+            .import_with_mnemonic(&username, &password, &mnemonic)
+            .await;
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err(),
+            FairOSError::User(FairOSUserError::InvalidMnemonic),
+        );
+    }
+
     #[tokio::test]
     async fn test_delete_user_succeeds() {
         let mut fairos = Client::new();
@@ -475,12 +831,15 @@ mod tests {
         let password = random_password();
         let res = fairos.signup(&username, &password, None).await;
         assert!(res.is_ok());
-        let (address, _) = res.unwrap();
+        let (address, mnemonic) = res.unwrap();
         let res = fairos.export_user(&username).await;
         assert!(res.is_ok());
         let export = res.unwrap();
         assert_eq!(export.username, username);
         assert_eq!(export.address, address);
+        let res = Client::verify_address(mnemonic.unwrap().expose_secret(), &export.address);
+        assert!(res.is_ok());
+        assert!(res.unwrap());
     }
 
     #[tokio::test]
@@ -0,0 +1,216 @@
+use crate::{error::FairOSError, transfer::join_path, Client, DirEntry, FileEntry};
+
+use futures::{stream, Stream};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WalkOrder {
+    BreadthFirst,
+    DepthFirst,
+}
+
+#[derive(Debug)]
+pub enum WalkEntryKind {
+    Dir(DirEntry),
+    File(FileEntry),
+}
+
+#[derive(Debug)]
+pub struct WalkEntry {
+    pub path: String,
+    pub depth: usize,
+    pub kind: WalkEntryKind,
+}
+
+impl WalkEntry {
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, WalkEntryKind::Dir(_))
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self.kind, WalkEntryKind::File(_))
+    }
+}
+
+struct WalkState<F> {
+    // Directories still to be listed, in breadth first order.
+    pending: VecDeque<(String, usize)>,
+    // The directory to list next, in depth first order.
+    expand: Option<(String, usize)>,
+    entries: VecDeque<WalkEntry>,
+    filter: F,
+}
+
+impl Client {
+    async fn walk_dir<F: FnMut(&WalkEntry) -> bool>(
+        &self,
+        username: &str,
+        pod: &str,
+        dir: &str,
+        depth: usize,
+        filter: &mut F,
+    ) -> Result<Vec<WalkEntry>, FairOSError> {
+        let (dirs, files) = self.ls(username, pod, dir).await?;
+        let dirs = dirs.into_iter().map(|entry| WalkEntry {
+            path: join_path(dir, &entry.name),
+            depth,
+            kind: WalkEntryKind::Dir(entry),
+        });
+        let files = files.into_iter().map(|entry| WalkEntry {
+            path: join_path(dir, &entry.name),
+            depth,
+            kind: WalkEntryKind::File(entry),
+        });
+        Ok(dirs.chain(files).filter(|entry| filter(entry)).collect())
+    }
+
+    // Entries directly below the root have a depth of 1. Directories rejected by the
+    // filter are not descended into.
+    pub fn walk<'a, F: FnMut(&WalkEntry) -> bool + 'a>(
+        &'a self,
+        username: &'a str,
+        pod: &'a str,
+        root: &'a str,
+        order: WalkOrder,
+        max_depth: Option<usize>,
+        filter: F,
+    ) -> impl Stream<Item = Result<WalkEntry, FairOSError>> + 'a {
+        let state = WalkState {
+            pending: VecDeque::from([(root.to_string(), 0)]),
+            expand: None,
+            entries: VecDeque::new(),
+            filter,
+        };
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some((dir, depth)) = state.expand.take() {
+                    match self
+                        .walk_dir(username, pod, &dir, depth + 1, &mut state.filter)
+                        .await
+                    {
+                        Ok(children) => {
+                            for entry in children.into_iter().rev() {
+                                state.entries.push_front(entry);
+                            }
+                        }
+                        Err(err) => return Some((Err(err), state)),
+                    }
+                }
+                if let Some(entry) = state.entries.pop_front() {
+                    let descend = match max_depth {
+                        Some(max_depth) => entry.depth < max_depth,
+                        None => true,
+                    };
+                    if entry.is_dir() && descend {
+                        let dir = (entry.path.clone(), entry.depth);
+                        match order {
+                            WalkOrder::BreadthFirst => state.pending.push_back(dir),
+                            WalkOrder::DepthFirst => state.expand = Some(dir),
+                        }
+                    }
+                    return Some((Ok(entry), state));
+                }
+                let (dir, depth) = state.pending.pop_front()?;
+                if max_depth == Some(0) {
+                    continue;
+                }
+                match self
+                    .walk_dir(username, pod, &dir, depth + 1, &mut state.filter)
+                    .await
+                {
+                    Ok(children) => state.entries.extend(children),
+                    Err(err) => return Some((Err(err), state)),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, WalkOrder};
+    use crate::BlockSize;
+    use futures::TryStreamExt;
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    #[tokio::test]
+    async fn test_walk_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        for dir in ["/a", "/a/b", "/c"] {
+            let res = fairos.mkdir(&username, &pod, dir).await;
+            assert!(res.is_ok());
+        }
+        for dir in ["/", "/a/b"] {
+            let res = fairos
+                .upload_buffer(
+                    &username,
+                    &pod,
+                    dir,
+                    "cat.txt",
+                    "meow".as_bytes(),
+                    mime::TEXT_PLAIN,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await;
+            assert!(res.is_ok());
+        }
+
+        let paths = |order, max_depth, prune: &'static str| {
+            let fairos = &fairos;
+            let username = &username;
+            let pod = &pod;
+            async move {
+                fairos
+                    .walk(username, pod, "/", order, max_depth, |entry| {
+                        entry.path != prune
+                    })
+                    .map_ok(|entry| entry.path)
+                    .try_collect::<Vec<_>>()
+                    .await
+            }
+        };
+        let res = paths(WalkOrder::BreadthFirst, None, "").await;
+        assert_eq!(
+            res.unwrap(),
+            vec!["/a", "/c", "/cat.txt", "/a/b", "/a/b/cat.txt"]
+        );
+        let res = paths(WalkOrder::DepthFirst, None, "").await;
+        assert_eq!(
+            res.unwrap(),
+            vec!["/a", "/a/b", "/a/b/cat.txt", "/c", "/cat.txt"]
+        );
+        let res = paths(WalkOrder::DepthFirst, Some(1), "").await;
+        assert_eq!(res.unwrap(), vec!["/a", "/c", "/cat.txt"]);
+        let res = paths(WalkOrder::BreadthFirst, None, "/a").await;
+        assert_eq!(res.unwrap(), vec!["/c", "/cat.txt"]);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    diff::{diff_snapshots, DiffEntry, DiffReason, DirDiff, Snapshot},
+    error::FairOSError,
+    runtime, Client,
+};
+
+use core::time::Duration;
+use futures::{stream, Stream};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchEvent {
+    Created(String),
+    Modified(String),
+    Deleted(String),
+}
+
+struct WatchState {
+    polled: bool,
+    snapshot: Option<Snapshot>,
+    events: VecDeque<WatchEvent>,
+}
+
+fn watch_events(dir: &str, diff: DirDiff) -> Vec<WatchEvent> {
+    let path = |path: &str| format!("{}{}", dir.trim_end_matches('/'), path);
+    let mut events = Vec::new();
+    for entry in diff.removed {
+        match entry {
+            DiffEntry::Dir(p) | DiffEntry::File(p) => events.push(WatchEvent::Deleted(path(&p))),
+        }
+    }
+    for entry in diff.added {
+        match entry {
+            DiffEntry::Dir(p) | DiffEntry::File(p) => events.push(WatchEvent::Created(path(&p))),
+        }
+    }
+    for change in diff.changed {
+        // An entry that turned from a file into a directory or back was replaced.
+        if change.reasons.contains(&DiffReason::Kind) {
+            events.push(WatchEvent::Deleted(path(&change.path)));
+            events.push(WatchEvent::Created(path(&change.path)));
+        } else {
+            events.push(WatchEvent::Modified(path(&change.path)));
+        }
+    }
+    events
+}
+
+impl Client {
+    pub fn watch_dir<'a>(
+        &'a self,
+        username: &'a str,
+        pod: &'a str,
+        dir: &'a str,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<WatchEvent, FairOSError>> + 'a {
+        let state = WatchState {
+            polled: false,
+            snapshot: None,
+            events: VecDeque::new(),
+        };
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(event) = state.events.pop_front() {
+                    return Some((Ok(event), state));
+                }
+                if state.polled {
+                    runtime::sleep(interval).await;
+                }
+                state.polled = true;
+                let snapshot = match self.snapshot(username, pod, dir).await {
+                    Ok(snapshot) => snapshot,
+                    Err(err) => return Some((Err(err), state)),
+                };
+                if let Some(previous) = &state.snapshot {
+                    let (diff, _) = diff_snapshots(previous, &snapshot, true);
+                    state.events.extend(watch_events(dir, diff));
+                }
+                state.snapshot = Some(snapshot);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{watch_events, Client, WatchEvent};
+    use crate::{
+        diff::{diff_snapshots, Snapshot, SnapshotEntry},
+        runtime, BlockSize,
+    };
+    use core::time::Duration;
+    use futures::{pin_mut, StreamExt};
+    use rand::{
+        distributions::{Alphanumeric, Uniform},
+        thread_rng, Rng,
+    };
+    use secrecy::SecretString;
+
+    fn random_name() -> String {
+        thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect()
+    }
+
+    fn random_password() -> SecretString {
+        let password = thread_rng()
+            .sample_iter(Uniform::new_inclusive(0, 255))
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        SecretString::from(password)
+    }
+
+    fn file(size: u32, modification_time: u64) -> SnapshotEntry {
+        SnapshotEntry::File {
+            size,
+            modification_time,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_events() {
+        let mut a = Snapshot::new();
+        a.insert("/cat.jpg".into(), file(100, 1));
+        a.insert("/dog.jpg".into(), file(100, 1));
+        a.insert("/notes".into(), file(10, 1));
+        let mut b = Snapshot::new();
+        b.insert("/cat.jpg".into(), file(100, 2));
+        b.insert("/bird.jpg".into(), file(100, 2));
+        b.insert("/notes".into(), SnapshotEntry::Dir);
+        let (diff, _) = diff_snapshots(&a, &b, true);
+        assert_eq!(
+            watch_events("/photos", diff),
+            vec![
+                WatchEvent::Deleted("/photos/dog.jpg".into()),
+                WatchEvent::Created("/photos/bird.jpg".into()),
+                WatchEvent::Modified("/photos/cat.jpg".into()),
+                WatchEvent::Deleted("/photos/notes".into()),
+                WatchEvent::Created("/photos/notes".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_dir_succeeds() {
+        let mut fairos = Client::new();
+        let username = random_name();
+        let password = random_password();
+        let res = fairos.signup(&username, &password, None).await;
+        assert!(res.is_ok());
+        let pod = random_name();
+        let res = fairos.create_pod(&username, &pod, &password).await;
+        assert!(res.is_ok());
+        let events = fairos.watch_dir(&username, &pod, "/", Duration::from_millis(100));
+        pin_mut!(events);
+        let upload = async {
+            runtime::sleep(Duration::from_millis(500)).await;
+            fairos
+                .upload_buffer(
+                    &username,
+                    &pod,
+                    "/",
+                    "cat.txt",
+                    "meow".as_bytes(),
+                    mime::TEXT_PLAIN,
+                    BlockSize::Kilobytes(1),
+                    None,
+                )
+                .await
+        };
+        let (res, event) = futures::join!(upload, events.next());
+        assert!(res.is_ok());
+        assert_eq!(event, Some(Ok(WatchEvent::Created("/cat.txt".into()))));
+    }
+}